@@ -0,0 +1,219 @@
+use crate::renditions::RenditionConfig;
+use crate::IWavsNftServiceTypes::WavsBatchMintOptions;
+use alloy_primitives::{keccak256, B256};
+use alloy_sol_types::SolValue;
+
+/// Largest batch the component generates, matching `WavsMinter.MAX_BATCH_SIZE`
+pub const MAX_BATCH_SIZE: usize = 10;
+
+/// Seeds of one token's generation
+#[derive(Debug, Clone, Copy)]
+pub struct Seeds {
+    pub llm: u32,
+    pub sd: i64,
+    /// Seed of the rarity trait draw
+    pub traits: B256,
+}
+
+impl Seeds {
+    /// Seeds of a single mint or update: the fixed generation seeds and the trigger's trait seed
+    pub fn single(trigger_seed: B256) -> Self {
+        Seeds { llm: 42, sd: 42, traits: trigger_seed }
+    }
+
+    /// Distinct seeds for the token at `index` of a batch, so variants of the
+    /// same prompt differ while staying reproducible
+    pub fn derived(trigger_seed: B256, index: usize) -> Self {
+        let mut data = trigger_seed.to_vec();
+        data.extend_from_slice(&(index as u32).to_be_bytes());
+        let hash = keccak256(data);
+        Seeds {
+            llm: u32::from_be_bytes(hash[..4].try_into().unwrap()),
+            // Stable Diffusion seeds are unsigned 32-bit values
+            sd: i64::from(u32::from_be_bytes(hash[4..8].try_into().unwrap())),
+            traits: hash,
+        }
+    }
+}
+
+/// The tokens requested by a BATCH_MINT trigger
+#[derive(Debug, Clone)]
+pub struct BatchMint {
+    /// One prompt per token, variants of a prompt are repeated
+    pub prompts: Vec<String>,
+    /// ABI-encoded `WavsMintOptions` applied to every token
+    pub options: Vec<u8>,
+}
+
+impl BatchMint {
+    /// Decode ABI-encoded `WavsBatchMintOptions`, falling back to the trigger's prompt
+    pub fn decode(prompt: &str, options: &[u8], max_size: usize) -> Result<Self, String> {
+        let WavsBatchMintOptions { prompts, variants, options } =
+            WavsBatchMintOptions::abi_decode(options, true)
+                .map_err(|e| format!("Failed to decode batch mint options: {}", e))?;
+
+        if variants == 0 {
+            return Err("Batch needs at least one variant".to_string());
+        }
+        let prompts = if prompts.is_empty() { vec![prompt.to_string()] } else { prompts };
+        if prompts.iter().any(|prompt| prompt.trim().is_empty()) {
+            return Err("Batch prompts must not be empty".to_string());
+        }
+
+        let size = prompts.len() * usize::from(variants);
+        if size > max_size {
+            return Err(format!("Batch of {} tokens exceeds the limit of {}", size, max_size));
+        }
+
+        let prompts = prompts
+            .into_iter()
+            .flat_map(|prompt| std::iter::repeat(prompt).take(usize::from(variants)))
+            .collect();
+        Ok(BatchMint { prompts, options: options.to_vec() })
+    }
+}
+
+/// Largest batch this operator accepts, `WAVS_ENV_MAX_BATCH_SIZE` lowers it to fit the fuel limit
+pub fn max_batch_size() -> Result<usize, String> {
+    match std::env::var("WAVS_ENV_MAX_BATCH_SIZE") {
+        Ok(size) => {
+            let size: usize =
+                size.parse().map_err(|e| format!("Invalid WAVS_ENV_MAX_BATCH_SIZE: {}", e))?;
+            Ok(size.min(MAX_BATCH_SIZE))
+        }
+        Err(_) => Ok(MAX_BATCH_SIZE),
+    }
+}
+
+/// Stable Diffusion steps of one token: its image, the other frames of the animated
+/// preview, and the upscale of the full-size rendition counted like one more generation
+pub fn token_steps(steps: u32, renditions: &RenditionConfig) -> u64 {
+    let generations =
+        renditions.animation_frames.max(1) + u32::from(renditions.upscale_factor.is_some());
+    u64::from(generations) * u64::from(steps)
+}
+
+/// Stable Diffusion step budget of a batch.
+///
+/// The batch size and the steps of each token are known before the first token is
+/// generated, so an oversized batch is rejected up front, and identically by every operator
+/// with the same configuration.
+pub struct Budget {
+    steps: Option<u64>,
+}
+
+impl Budget {
+    /// Budget from `WAVS_ENV_BATCH_STEP_BUDGET`, unlimited when unset
+    pub fn from_env() -> Result<Self, String> {
+        let steps = match std::env::var("WAVS_ENV_BATCH_STEP_BUDGET") {
+            Ok(steps) => Some(
+                steps.parse().map_err(|e| format!("Invalid WAVS_ENV_BATCH_STEP_BUDGET: {}", e))?,
+            ),
+            Err(_) => None,
+        };
+        Ok(Budget { steps })
+    }
+
+    /// Check `tokens` tokens of `steps_per_token` steps each fit the budget
+    pub fn check(&self, tokens: usize, steps_per_token: u64) -> Result<(), String> {
+        let Some(limit) = self.steps else {
+            return Ok(());
+        };
+        let steps = (tokens as u64).saturating_mul(steps_per_token);
+        if steps > limit {
+            return Err(format!(
+                "Batch of {} tokens needs {} Stable Diffusion steps, over the budget of {}",
+                tokens, steps, limit
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::MintOptions;
+
+    fn encode(prompts: &[&str], variants: u16) -> Vec<u8> {
+        WavsBatchMintOptions {
+            prompts: prompts.iter().map(|prompt| prompt.to_string()).collect(),
+            variants,
+            options: MintOptions::default().abi_encode().into(),
+        }
+        .abi_encode()
+    }
+
+    #[test]
+    fn decodes_batches() {
+        let batch = BatchMint::decode("trigger", &encode(&["a", "b"], 2), MAX_BATCH_SIZE).unwrap();
+        assert_eq!(batch.prompts, ["a", "a", "b", "b"]);
+        assert_eq!(batch.options, MintOptions::default().abi_encode());
+
+        let batch = BatchMint::decode("trigger", &encode(&[], 3), MAX_BATCH_SIZE).unwrap();
+        assert_eq!(batch.prompts, ["trigger", "trigger", "trigger"]);
+    }
+
+    #[test]
+    fn rejects_invalid_batches() {
+        let decode = |prompts: &[&str], variants, max_size| {
+            BatchMint::decode("trigger", &encode(prompts, variants), max_size).unwrap_err()
+        };
+        assert_eq!(decode(&["a"], 0, MAX_BATCH_SIZE), "Batch needs at least one variant");
+        assert_eq!(decode(&["a", " "], 1, MAX_BATCH_SIZE), "Batch prompts must not be empty");
+        assert_eq!(
+            decode(&["a", "b", "c"], 4, MAX_BATCH_SIZE),
+            "Batch of 12 tokens exceeds the limit of 10"
+        );
+        assert_eq!(decode(&["a", "b"], 1, 1), "Batch of 2 tokens exceeds the limit of 1");
+        assert!(BatchMint::decode("trigger", &[0x01], MAX_BATCH_SIZE).is_err());
+    }
+
+    #[test]
+    fn derives_distinct_reproducible_seeds() {
+        let trigger_seed = keccak256(b"trigger");
+        let first = Seeds::derived(trigger_seed, 0);
+        let second = Seeds::derived(trigger_seed, 1);
+
+        assert_eq!(first.traits, Seeds::derived(trigger_seed, 0).traits);
+        assert_ne!(first.traits, second.traits);
+        assert_ne!(first.traits, trigger_seed);
+        assert_ne!((first.llm, first.sd), (second.llm, second.sd));
+        assert!((0..=i64::from(u32::MAX)).contains(&first.sd));
+
+        let mut data = trigger_seed.to_vec();
+        data.extend_from_slice(&1u32.to_be_bytes());
+        assert_eq!(second.traits, keccak256(data));
+    }
+
+    #[test]
+    fn limits_the_steps_of_a_batch() {
+        let budget = Budget { steps: Some(90) };
+        assert!(budget.check(3, 30).is_ok());
+        assert_eq!(
+            budget.check(4, 30).unwrap_err(),
+            "Batch of 4 tokens needs 120 Stable Diffusion steps, over the budget of 90"
+        );
+        assert!(Budget { steps: None }.check(MAX_BATCH_SIZE, u64::MAX).is_ok());
+    }
+
+    #[test]
+    fn counts_frames_and_upscales_in_token_steps() {
+        let renditions = |animation_frames, upscale_factor| RenditionConfig {
+            thumbnail_size: 256,
+            upscale_factor,
+            animation_frames,
+        };
+        assert_eq!(token_steps(30, &renditions(0, None)), 30);
+        assert_eq!(token_steps(30, &renditions(1, None)), 30);
+        assert_eq!(token_steps(30, &renditions(4, None)), 120);
+        assert_eq!(token_steps(30, &renditions(4, Some(2.0))), 150);
+
+        let budget = Budget { steps: Some(300) };
+        assert!(budget.check(2, token_steps(30, &renditions(4, Some(2.0)))).is_ok());
+        assert_eq!(
+            budget.check(3, token_steps(30, &renditions(4, Some(2.0)))).unwrap_err(),
+            "Batch of 3 tokens needs 450 Stable Diffusion steps, over the budget of 300"
+        );
+    }
+}
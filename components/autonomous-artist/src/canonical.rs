@@ -0,0 +1,254 @@
+use serde::Serialize;
+use serde_json::{Number, Value};
+use unicode_normalization::UnicodeNormalization;
+
+/// Serialize a value to canonical JSON.
+///
+/// The metadata CID must not depend on struct field order or number and string
+/// formatting, otherwise operators running different versions produce different
+/// token URIs. The output follows RFC 8785 (JCS) closely:
+/// - object keys are sorted by their UTF-16 code units
+/// - no insignificant whitespace
+/// - strings and keys are NFC normalized, only `"`, `\` and control characters are escaped
+/// - numbers are written as ECMAScript writes doubles, independently of serde_json
+///
+/// Integers beyond 2^53 have no exact double and are rejected rather than rounded.
+pub fn to_canonical_json<T: Serialize>(value: &T) -> Result<String, String> {
+    let value =
+        serde_json::to_value(value).map_err(|e| format!("JSON serialization error: {}", e))?;
+    let mut out = String::new();
+    write_value(&value, &mut out)?;
+    Ok(out)
+}
+
+fn write_value(value: &Value, out: &mut String) -> Result<(), String> {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => write_number(n, out)?,
+        Value::String(s) => write_string(s, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<(String, &Value)> =
+                map.iter().map(|(k, v)| (k.nfc().collect::<String>(), v)).collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+
+            out.push('{');
+            for (i, (key, item)) in entries.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(key, out);
+                out.push(':');
+                write_value(item, out)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+/// Largest integer magnitude a double represents exactly
+pub(crate) const MAX_EXACT_INTEGER: u64 = 1 << 53;
+
+fn write_number(n: &Number, out: &mut String) -> Result<(), String> {
+    if let Some(f) = n.as_f64().filter(|_| n.is_f64()) {
+        out.push_str(&format_double(f));
+        return Ok(());
+    }
+    // Integers in the exact range are written as ECMAScript writes the equal double
+    let magnitude = n.as_u64().or_else(|| n.as_i64().map(i64::unsigned_abs)).unwrap_or(u64::MAX);
+    if magnitude > MAX_EXACT_INTEGER {
+        return Err(format!("Integer {} cannot be represented exactly in canonical JSON", n));
+    }
+    out.push_str(&n.to_string());
+    Ok(())
+}
+
+/// Format a finite double as ECMAScript's `Number.prototype.toString`, as RFC 8785 requires
+fn format_double(value: f64) -> String {
+    if value == 0.0 {
+        // Covers -0
+        return "0".to_string();
+    }
+
+    // Rust's shortest representation has as few digits as ECMAScript, but when several
+    // k-digit candidates round-trip ECMAScript wants the one closest to the value, which
+    // formatting at that precision gives
+    let shortest = format!("{:e}", value.abs());
+    let k = shortest.split_once('e').map_or(0, |(m, _)| m.chars().filter(|c| *c != '.').count());
+    let closest = format!("{:.*e}", k.saturating_sub(1), value.abs());
+    let scientific = if closest.parse::<f64>() == Ok(value.abs()) { closest } else { shortest };
+    let (mantissa, exponent) = scientific.split_once('e').expect("exponent is always written");
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    // The value is 0.d1d2...dk * 10^n
+    let n = exponent.parse::<i32>().expect("exponent is an integer") + 1;
+
+    let mut out = String::new();
+    if value < 0.0 {
+        out.push('-');
+    }
+    if k <= n && n <= 21 {
+        out.push_str(&digits);
+        out.push_str(&"0".repeat((n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        out.push_str(&digits[..n as usize]);
+        out.push('.');
+        out.push_str(&digits[n as usize..]);
+    } else if -6 < n && n <= 0 {
+        out.push_str("0.");
+        out.push_str(&"0".repeat(-n as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        out.push_str(&format!("e{}{}", if n > 0 { "+" } else { "-" }, (n - 1).abs()));
+    }
+    out
+}
+
+fn write_string(s: &str, out: &mut String) {
+    let normalized: String = s.nfc().collect();
+    out.push('"');
+    for c in normalized.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{08}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{0c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nft::{Attribute, DisplayType, NFTMetadata};
+    use crate::provenance::LlmProvenance;
+    use serde_json::json;
+
+    #[test]
+    fn sorts_keys_and_strips_whitespace() {
+        let value = json!({ "b": 1, "a": { "d": [1, 2], "c": null }, "A": true });
+        assert_eq!(
+            to_canonical_json(&value).unwrap(),
+            r#"{"A":true,"a":{"c":null,"d":[1,2]},"b":1}"#
+        );
+    }
+
+    #[test]
+    fn sorts_keys_by_utf16_code_units() {
+        // U+E000 sorts after U+1F600 in UTF-8 byte order but before it in UTF-16
+        let value = json!({ "\u{1F600}": 1, "\u{E000}": 2 });
+        assert_eq!(to_canonical_json(&value).unwrap(), "{\"\u{1F600}\":1,\"\u{E000}\":2}");
+    }
+
+    #[test]
+    fn normalizes_numbers() {
+        let value = json!([1.0, -0.0, 0.5, -12, 9007199254740992u64, 1e300, 0.7]);
+        assert_eq!(to_canonical_json(&value).unwrap(), "[1,0,0.5,-12,9007199254740992,1e+300,0.7]");
+
+        assert!(to_canonical_json(&json!([9007199254740993u64])).is_err());
+        assert!(to_canonical_json(&json!([-9007199254740993i64])).is_err());
+    }
+
+    #[test]
+    fn formats_rfc_8785_number_vectors() {
+        // RFC 8785 appendix B, as IEEE 754 bit patterns
+        let vectors = [
+            (0x0000000000000000, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x8000000000000001, "-5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0xffefffffffffffff, "-1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0xc340000000000000, "-9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+            (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+            (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+            (0x444b1ae4d6e2ef50, "1e+21"),
+            (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+            (0x3eb0c6f7a0b5ed8d, "0.000001"),
+            (0x41b3de4355555553, "333333333.3333332"),
+            (0x41b3de4355555554, "333333333.33333325"),
+            (0x41b3de4355555555, "333333333.3333333"),
+            (0x41b3de4355555556, "333333333.3333334"),
+            (0x41b3de4355555557, "333333333.33333343"),
+            (0xbecbf647612f3696, "-0.0000033333333333333333"),
+            (0x43143ff3c1cb0959, "1424953923781206.2"),
+        ];
+        for (bits, expected) in vectors {
+            assert_eq!(format_double(f64::from_bits(bits)), expected, "{:#018x}", bits);
+        }
+    }
+
+    #[test]
+    fn writes_generation_parameters_as_decimals() {
+        let llm = LlmProvenance {
+            model: "llama3.2".to_string(),
+            context_window: 1024,
+            max_tokens: 1024,
+            seed: 42,
+            temperature: 0.7,
+            top_p: 0.9,
+        };
+        assert_eq!(
+            to_canonical_json(&llm).unwrap(),
+            r#"{"context_window":1024,"max_tokens":1024,"model":"llama3.2","seed":42,"temperature":0.7,"top_p":0.9}"#
+        );
+    }
+
+    #[test]
+    fn normalizes_and_escapes_strings() {
+        // "e" followed by a combining acute accent composes to U+00E9
+        let value = json!({ "cafe\u{0301}": "caf\u{0065}\u{0301} \"q\" \\ \n\u{01}/<>" });
+        assert_eq!(
+            to_canonical_json(&value).unwrap(),
+            "{\"caf\u{e9}\":\"caf\u{e9} \\\"q\\\" \\\\ \\n\\u0001/<>\"}"
+        );
+    }
+
+    #[test]
+    fn pins_metadata_bytes() {
+        let metadata = NFTMetadata {
+            name: "Rhizome".to_string(),
+            description: "A line of flight.".to_string(),
+            image: "ipfs://bafy/nft_image.png".to_string(),
+            attributes: vec![
+                Attribute::new("Prompt", "How to become a great artist?"),
+                Attribute::new("Generation", 2u32)
+                    .with_display_type(DisplayType::Number)
+                    .with_max_value(10.0),
+            ],
+            background_color: Some("ffffff".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            to_canonical_json(&metadata).unwrap(),
+            r#"{"attributes":[{"trait_type":"Prompt","value":"How to become a great artist?"},{"display_type":"number","max_value":10,"trait_type":"Generation","value":2}],"background_color":"ffffff","description":"A line of flight.","image":"ipfs://bafy/nft_image.png","name":"Rhizome"}"#
+        );
+    }
+}
@@ -1,15 +1,18 @@
 use crate::bindings::host::get_eth_chain_config;
+use crate::nft::LogLocation;
+use crate::IWavsNftServiceTypes::{WavsNftMint, WavsNftReveal, WavsNftUpdate};
 use alloy_network::Ethereum;
-use alloy_primitives::{Address, TxKind, U256};
+use alloy_primitives::{Address, LogData, TxKind, B256, U256};
 use alloy_provider::{Provider, RootProvider};
-use alloy_rpc_types::TransactionInput;
-use alloy_sol_types::{sol, SolCall};
+use alloy_rpc_types::{BlockNumberOrTag, Filter, TransactionInput};
+use alloy_sol_types::{sol, SolCall, SolEvent};
 use wavs_wasi_chain::ethereum::new_eth_provider;
 use wstd::runtime::block_on;
 
 sol! {
     interface IERC721 {
         function balanceOf(address owner) external view returns (uint256);
+        function tokenURI(uint256 tokenId) external view returns (string);
     }
 }
 
@@ -31,3 +34,121 @@ pub fn query_nft_ownership(address: Address, nft_contract: Address) -> Result<bo
         Ok(balance > U256::ZERO)
     })
 }
+
+/// Provider for one of the chains WAVS is configured for
+fn provider(chain_name: &str) -> Result<RootProvider<Ethereum>, String> {
+    let chain_config =
+        get_eth_chain_config(chain_name).ok_or_else(|| format!("Unknown chain: {}", chain_name))?;
+    let endpoint = chain_config
+        .http_endpoint
+        .ok_or_else(|| format!("No HTTP endpoint configured for chain: {}", chain_name))?;
+    Ok(new_eth_provider::<Ethereum>(endpoint))
+}
+
+/// Current URI of a token on the chain its contract lives on
+pub fn query_token_uri(
+    chain_name: &str,
+    nft_contract: Address,
+    token_id: U256,
+) -> Result<String, String> {
+    let provider = provider(chain_name)?;
+    block_on(async move {
+        let token_uri_call = IERC721::tokenURICall { tokenId: token_id };
+        let tx = alloy_rpc_types::eth::TransactionRequest {
+            to: Some(TxKind::Call(nft_contract)),
+            input: TransactionInput { input: Some(token_uri_call.abi_encode().into()), data: None },
+            ..Default::default()
+        };
+
+        let result = provider.call(&tx).await.map_err(|e| e.to_string())?;
+        let decoded = IERC721::tokenURICall::abi_decode_returns(&result, true)
+            .map_err(|e| format!("Failed to decode tokenURI: {}", e))?;
+        Ok(decoded._0)
+    })
+}
+
+/// Check an event log was emitted as given, so a raw trigger can't claim an event that
+/// never happened. `emitter`, `topics` and `data` must match a log of the block exactly.
+pub fn verify_log(
+    chain_name: &str,
+    emitter: Address,
+    block_number: u64,
+    topics: &[B256],
+    data: &[u8],
+) -> Result<(), String> {
+    let signature = *topics.first().ok_or("Event log has no topics")?;
+    let filter = Filter::new()
+        .address(emitter)
+        .from_block(block_number)
+        .to_block(block_number)
+        .event_signature(signature);
+    let provider = provider(chain_name)?;
+    let logs =
+        block_on(async move { provider.get_logs(&filter).await.map_err(|e| e.to_string()) })?;
+
+    logs.iter()
+        .find(|log| {
+            log.address() == emitter
+                && log.inner.data.topics() == topics
+                && log.inner.data.data[..] == data[..]
+        })
+        .map(|_| ())
+        .ok_or_else(|| {
+            format!(
+                "Event not found in block {} of {}, emitted by {}",
+                block_number, chain_name, emitter
+            )
+        })
+}
+
+/// Where the `WavsNftMint`, `WavsNftUpdate` or `WavsNftReveal` event that last set the URI of
+/// a token to `token_uri` was emitted. The contract's whole history is searched, filtered by
+/// token, so the chain's RPC endpoint must serve log queries from the earliest block.
+pub fn find_token_uri_event(
+    chain_name: &str,
+    nft_contract: Address,
+    token_id: U256,
+    token_uri: &str,
+) -> Result<LogLocation, String> {
+    let filter = Filter::new()
+        .address(nft_contract)
+        .event_signature(vec![
+            WavsNftMint::SIGNATURE_HASH,
+            WavsNftUpdate::SIGNATURE_HASH,
+            WavsNftReveal::SIGNATURE_HASH,
+        ])
+        .topic2(B256::from(token_id))
+        .from_block(BlockNumberOrTag::Earliest)
+        .to_block(BlockNumberOrTag::Latest);
+    let provider = provider(chain_name)?;
+    let logs =
+        block_on(async move { provider.get_logs(&filter).await.map_err(|e| e.to_string()) })?;
+
+    let log = logs
+        .iter()
+        .rev()
+        .find(|log| set_token_uri(&log.inner.data).as_deref() == Some(token_uri))
+        .ok_or_else(|| format!("No event set the current URI of token {}", token_id))?;
+    Ok(LogLocation {
+        block_number: log.block_number.ok_or("Event log has no block number")?,
+        transaction_hash: log
+            .transaction_hash
+            .ok_or("Event log has no transaction hash")?
+            .to_string(),
+        log_index: log.log_index.ok_or("Event log has no index")?,
+    })
+}
+
+/// Token URI a `WavsNftMint`, `WavsNftUpdate` or `WavsNftReveal` log sets
+fn set_token_uri(log: &LogData) -> Option<String> {
+    let signature = *log.topics().first()?;
+    if signature == WavsNftMint::SIGNATURE_HASH {
+        WavsNftMint::decode_log_data(log, true).ok().map(|event| event.tokenURI)
+    } else if signature == WavsNftUpdate::SIGNATURE_HASH {
+        WavsNftUpdate::decode_log_data(log, true).ok().map(|event| event.tokenURI)
+    } else if signature == WavsNftReveal::SIGNATURE_HASH {
+        WavsNftReveal::decode_log_data(log, true).ok().map(|event| event.tokenURI)
+    } else {
+        None
+    }
+}
@@ -1,4 +1,7 @@
+use crate::media;
+use crate::procedural::ProceduralArt;
 use anyhow::Result;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use wstd::{
     http::{Client, IntoBody, Request},
@@ -13,13 +16,112 @@ struct StableDiffusionRequest {
     negative_prompt: String,
     seed: i64,            // Fixed seed for determinism
     steps: u32,           // Number of inference steps
-    cfg_scale: f32,       // Guidance scale
+    cfg_scale: f64,       // Guidance scale
     width: u32,           // Image width
     height: u32,          // Image height
     sampler_name: String, // Specific sampler to use
     model: String,        // Specific model checkpoint
 }
 
+/// A LoRA applied on top of the model checkpoint
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Lora {
+    pub name: String,
+    pub weight: f64,
+}
+
+/// Fixed generation parameters, recorded in the token provenance
+#[derive(Debug, Clone)]
+pub struct SdParams {
+    pub negative_prompt: String,
+    pub seed: i64,
+    pub steps: u32,
+    pub cfg_scale: f64,
+    pub width: u32,
+    pub height: u32,
+    pub sampler_name: String,
+    pub model: String,
+    pub loras: Vec<Lora>,
+}
+
+impl Default for SdParams {
+    fn default() -> Self {
+        SdParams {
+            negative_prompt: "blurry, bad quality, distorted, nonsquare, not full, borders"
+                .to_string(),
+            seed: 42, // Always use the same seed
+            steps: 30,
+            cfg_scale: 7.0,
+            width: 512,
+            height: 512,
+            sampler_name: "DPM++ 2M Karras".to_string(),
+            model: "v1-5-pruned-emaonly".to_string(), // Match the model specified by the user
+            loras: Vec::new(),
+        }
+    }
+}
+
+/// Where images are generated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// The Stable Diffusion server at `WAVS_ENV_SD_API_URL`
+    StableDiffusion,
+    /// Abstract art drawn from the prompt hash and traits, without a network
+    Procedural,
+}
+
+impl Backend {
+    /// Read `WAVS_ENV_IMAGE_BACKEND`, a comma separated list of backends tried in order.
+    ///
+    /// Stable Diffusion alone by default, `sd,procedural` falls back to procedural
+    /// art when the server fails and `procedural` never calls it.
+    pub fn from_env() -> Result<Vec<Backend>, String> {
+        let Ok(backends) = std::env::var("WAVS_ENV_IMAGE_BACKEND") else {
+            return Ok(vec![Backend::StableDiffusion]);
+        };
+        let backends = backends
+            .split(',')
+            .map(|backend| match backend.trim() {
+                "sd" | "stable_diffusion" => Ok(Backend::StableDiffusion),
+                "procedural" => Ok(Backend::Procedural),
+                other => Err(format!("Unsupported WAVS_ENV_IMAGE_BACKEND: {}", other)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(backends)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::StableDiffusion => "stable_diffusion",
+            Backend::Procedural => "procedural",
+        }
+    }
+}
+
+/// Generate an image with the first of `backends` that succeeds, returning it with that backend
+pub fn generate(
+    prompt: &str,
+    params: &SdParams,
+    art: &ProceduralArt,
+    backends: &[Backend],
+) -> Result<(Vec<u8>, Backend), String> {
+    let mut errors = Vec::new();
+    for &backend in backends {
+        let result = match backend {
+            Backend::StableDiffusion => generate_deterministic_image(prompt, params),
+            Backend::Procedural => art.render_png(params.seed, params.width, params.height),
+        };
+        match result {
+            Ok(bytes) => return Ok((bytes, backend)),
+            Err(e) => {
+                eprintln!("Image generation with {} failed: {}", backend.name(), e);
+                errors.push(format!("{}: {}", backend.name(), e));
+            }
+        }
+    }
+    Err(format!("Image generation failed. {}", errors.join("; ")))
+}
+
 // Response structure with flexible parameter handling
 #[derive(Deserialize, Debug)]
 struct StableDiffusionResponse {
@@ -28,8 +130,8 @@ struct StableDiffusionResponse {
     _parameters: Option<serde_json::Value>, // Use generic Value to handle any response structure
 }
 
-/// Generate a deterministic image using Stable Diffusion API
-pub fn generate_deterministic_image(prompt: &str) -> Result<String, String> {
+/// Generate a deterministic image using Stable Diffusion API, returning the verified image bytes
+pub fn generate_deterministic_image(prompt: &str, params: &SdParams) -> Result<Vec<u8>, String> {
     block_on(async move {
         // Get API URL from environment variable
         let api_url = std::env::var("WAVS_ENV_SD_API_URL")
@@ -38,18 +140,23 @@ pub fn generate_deterministic_image(prompt: &str) -> Result<String, String> {
         // Get API key from environment variable
         let api_key = std::env::var("WAVS_ENV_SD_API_KEY").unwrap_or_default();
 
+        // LoRAs are selected with the WebUI prompt syntax
+        let mut prompt = prompt.to_string();
+        for lora in &params.loras {
+            prompt.push_str(&format!(" <lora:{}:{}>", lora.name, lora.weight));
+        }
+
         // Fixed parameters for deterministic generation
         let request_data = StableDiffusionRequest {
-            prompt: prompt.to_string(),
-            negative_prompt: "blurry, bad quality, distorted, nonsquare, not full, borders"
-                .to_string(),
-            seed: 42, // Always use the same seed
-            steps: 30,
-            cfg_scale: 7.0,
-            width: 512,
-            height: 512,
-            sampler_name: "DPM++ 2M Karras".to_string(),
-            model: "v1-5-pruned-emaonly".to_string(), // Match the model specified by the user
+            prompt,
+            negative_prompt: params.negative_prompt.clone(),
+            seed: params.seed,
+            steps: params.steps,
+            cfg_scale: params.cfg_scale,
+            width: params.width,
+            height: params.height,
+            sampler_name: params.sampler_name.clone(),
+            model: params.model.clone(),
         };
 
         // Serialize to JSON
@@ -132,8 +239,85 @@ pub fn generate_deterministic_image(prompt: &str) -> Result<String, String> {
             }
         };
 
-        // Format as data URI with proper MIME type
-        // Stable Diffusion typically returns PNG images
-        Ok(format!("data:image/png;base64,{}", image_base64))
+        decode_image(&image_base64)
     })
 }
+
+// Request structure for the Stable Diffusion extras endpoint
+#[derive(Serialize)]
+struct UpscaleRequest {
+    image: String, // Base64 encoded PNG
+    upscaling_resize: f32,
+    upscaler_1: String,
+}
+
+#[derive(Deserialize)]
+struct UpscaleResponse {
+    image: String, // Base64 encoded PNG
+}
+
+/// Upscale a PNG by `factor` with the Stable Diffusion extras endpoint, returning the verified image bytes
+pub fn upscale_image(png: &[u8], factor: f32) -> Result<Vec<u8>, String> {
+    block_on(async move {
+        // The extras endpoint lives next to txt2img unless configured separately
+        let api_url = std::env::var("WAVS_ENV_SD_EXTRAS_API_URL").unwrap_or_else(|_| {
+            std::env::var("WAVS_ENV_SD_API_URL")
+                .unwrap_or_else(|_| "http://localhost:7860/sdapi/v1/txt2img".to_string())
+                .replace("/txt2img", "/extra-single-image")
+        });
+        let api_key = std::env::var("WAVS_ENV_SD_API_KEY").unwrap_or_default();
+        let upscaler =
+            std::env::var("WAVS_ENV_SD_UPSCALER").unwrap_or_else(|_| "R-ESRGAN 4x+".to_string());
+
+        let json_data = serde_json::to_string(&UpscaleRequest {
+            image: base64::engine::general_purpose::STANDARD.encode(png),
+            upscaling_resize: factor,
+            upscaler_1: upscaler,
+        })
+        .map_err(|e| format!("JSON serialization error: {}", e))?;
+
+        let mut builder = Request::post(&api_url)
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json");
+        if !api_key.is_empty() {
+            builder = builder.header("Authorization", &format!("Bearer {}", api_key));
+        }
+        let req = builder.body(json_data.as_bytes().into_body()).map_err(|e| e.to_string())?;
+
+        let mut response =
+            Client::new().send(req).await.map_err(|e| format!("Request failed: {}", e))?;
+
+        let mut body_buf = Vec::new();
+        response
+            .body_mut()
+            .read_to_end(&mut body_buf)
+            .await
+            .map_err(|e| format!("Failed to read response body: {}", e))?;
+
+        if response.status() != 200 {
+            let error_text = String::from_utf8_lossy(&body_buf);
+            return Err(format!("API error: status {} - {}", response.status(), error_text));
+        }
+
+        let upscaled: UpscaleResponse = serde_json::from_slice(&body_buf)
+            .map_err(|e| format!("Failed to parse upscale response: {}", e))?;
+        decode_image(&upscaled.image)
+    })
+}
+
+/// Decode a base64 image from the API and check it is a complete image of an accepted size.
+///
+/// The format is taken from the bytes, Stable Diffusion servers usually return
+/// PNG but can be configured for JPEG or WebP, and some prefix a data URI header.
+fn decode_image(image_base64: &str) -> Result<Vec<u8>, String> {
+    let image_base64 = match image_base64.split_once(";base64,") {
+        Some((header, data)) if header.starts_with("data:") => data,
+        _ => image_base64,
+    };
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(image_base64)
+        .map_err(|e| format!("Failed to decode base64 image: {}", e))?;
+    let media_type = media::verify(&bytes, media::max_image_bytes()?)?;
+    eprintln!("Received {} image of {} bytes", media_type.content_type(), bytes.len());
+    Ok(bytes)
+}
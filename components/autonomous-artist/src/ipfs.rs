@@ -1,17 +1,19 @@
 use anyhow::Result;
+use base64::Engine;
 use serde::Deserialize;
 use std::{
     fs::File,
     io::{Read, Write},
 };
+use wavs_nft_ipfs::AddRequest;
 use wstd::io::AsyncRead;
 use wstd::{
     http::{IntoBody, Request},
     runtime::block_on,
 };
 
-/// Uploads a file using multipart request to IPFS
-async fn upload_to_ipfs(file_path: &str, ipfs_url: &str) -> Result<String> {
+/// Uploads a file using multipart request to IPFS, the part is sent as `content_type`
+async fn upload_to_ipfs(file_path: &str, content_type: &str, ipfs_url: &str) -> Result<String> {
     let api_key = std::env::var("WAVS_ENV_LIGHTHOUSE_API_KEY")
         .map_err(|e| anyhow::anyhow!("Failed to get API key: {}", e))?;
 
@@ -21,25 +23,13 @@ async fn upload_to_ipfs(file_path: &str, ipfs_url: &str) -> Result<String> {
     let mut file_bytes = Vec::new();
     file.read_to_end(&mut file_bytes)?;
 
-    // define multipart request boundary
-    let boundary = "----RustBoundary";
-
-    // construct the body
-    let body = format!(
-        "--{}\r\n\
-        Content-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n\
-        Content-Type: application/octet-stream\r\n\r\n",
-        boundary, file_path
-    );
-
-    let mut request_body = body.into_bytes();
-    request_body.extend_from_slice(&file_bytes);
-    request_body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    // The multipart form is built as the host tools build it
+    let form = AddRequest::new(file_path, content_type, &file_bytes);
 
     let request = Request::post(ipfs_url)
         .header("Authorization", &format!("Bearer {}", api_key))
-        .header("Content-Type", &format!("multipart/form-data; boundary={}", boundary))
-        .body(request_body.into_body())?;
+        .header("Content-Type", &form.content_type)
+        .body(form.body.into_body())?;
 
     let mut response = wstd::http::Client::new().send(request).await?;
 
@@ -116,7 +106,7 @@ pub async fn upload_json_to_ipfs(json_data: &str, ipfs_url: &str) -> Result<Stri
     file.write_all(json_data.as_bytes())?;
 
     // Upload the file
-    let hash = upload_to_ipfs(&temp_path, ipfs_url).await?;
+    let hash = upload_to_ipfs(&temp_path, "application/json", ipfs_url).await?;
 
     // Clean up the temporary file
     delete_file(&temp_path)?;
@@ -129,6 +119,7 @@ pub async fn upload_json_to_ipfs(json_data: &str, ipfs_url: &str) -> Result<Stri
 pub async fn upload_image_to_ipfs(
     image_data: &[u8],
     filename: &str,
+    content_type: &str,
     ipfs_url: &str,
 ) -> Result<String> {
     // Create a temporary file to store the image data
@@ -143,7 +134,7 @@ pub async fn upload_image_to_ipfs(
     file.write_all(image_data)?;
 
     // Upload the file
-    let hash = upload_to_ipfs(&temp_path, ipfs_url).await?;
+    let hash = upload_to_ipfs(&temp_path, content_type, ipfs_url).await?;
 
     // Clean up the temporary file
     delete_file(&temp_path)?;
@@ -182,18 +173,10 @@ pub fn upload_nft_content(content_type: &str, content: &[u8], ipfs_url: &str) ->
             upload_json_to_ipfs(json_str, ipfs_url).await?
         } else {
             // It's an image or other binary content
-            let extension = match content_type {
-                "image/png" => "png",
-                "image/jpeg" => "jpg",
-                "image/gif" => "gif",
-                "image/svg+xml" => "svg",
-                _ => "bin", // Default extension for unknown types
-            };
-
-            let filename = format!("nft_image.{}", extension);
+            let filename = format!("nft_image.{}", wavs_nft_ipfs::extension(content_type));
 
             // Upload the image and return the IPFS URI
-            upload_image_to_ipfs(content, &filename, ipfs_url).await?
+            upload_image_to_ipfs(content, &filename, content_type, ipfs_url).await?
         };
 
         // Log the upload
@@ -203,3 +186,124 @@ pub fn upload_nft_content(content_type: &str, content: &[u8], ipfs_url: &str) ->
         Ok(ipfs_uri)
     })
 }
+
+/// Extract the CID from an `ipfs://CID[/path]` URI or a gateway URL containing `/ipfs/CID`
+pub fn cid_from_uri(uri: &str) -> Option<String> {
+    let rest = match uri.strip_prefix("ipfs://") {
+        Some(rest) => rest,
+        None => &uri[uri.find("/ipfs/")? + "/ipfs/".len()..],
+    };
+    let cid = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    (!cid.is_empty()).then(|| cid.to_string())
+}
+
+/// Resolve a token or image URI to its content.
+/// Supports `ipfs://` (through the configured gateway), `data:` and `http(s)://` URIs.
+pub fn fetch_uri(uri: &str) -> Result<Vec<u8>> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (media_type, payload) =
+            data.split_once(',').ok_or_else(|| anyhow::anyhow!("Invalid data URI"))?;
+        return if media_type.ends_with(";base64") {
+            Ok(base64::engine::general_purpose::STANDARD.decode(payload)?)
+        } else {
+            Ok(percent_decode(payload))
+        };
+    }
+
+    let url = match uri.strip_prefix("ipfs://") {
+        Some(path) => {
+            let gateway = std::env::var("WAVS_ENV_IPFS_GATEWAY_URL")
+                .unwrap_or_else(|_| "https://gateway.lighthouse.storage/ipfs/".to_string());
+            format!("{}/{}", gateway.trim_end_matches('/'), path)
+        }
+        None => uri.to_string(),
+    };
+
+    block_on(async move {
+        let request = Request::get(&url).body(wstd::io::empty())?;
+        let mut response = wstd::http::Client::new().send(request).await?;
+
+        let mut body_buf = Vec::new();
+        response.body_mut().read_to_end(&mut body_buf).await?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "Failed to fetch {}. Status: {:?}",
+                url,
+                response.status()
+            ));
+        }
+        Ok(body_buf)
+    })
+}
+
+/// Decode the `%XX` escapes of a data URI payload, leaving malformed escapes as they are
+fn percent_decode(payload: &str) -> Vec<u8> {
+    let bytes = payload.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// Read JSON configuration given either inline or as a URI supported by `fetch_uri`
+pub fn fetch_json_config(source: &str) -> Result<Vec<u8>> {
+    let source = source.trim();
+    if source.starts_with('{') || source.starts_with('[') {
+        return Ok(source.as_bytes().to_vec());
+    }
+    eprintln!("Fetching configuration from {}", source);
+    fetch_uri(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CID: &str = "bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku";
+
+    #[test]
+    fn extracts_cids() {
+        assert_eq!(cid_from_uri(&format!("ipfs://{}", CID)).as_deref(), Some(CID));
+        assert_eq!(cid_from_uri(&format!("ipfs://{}/metadata.json", CID)).as_deref(), Some(CID));
+        assert_eq!(
+            cid_from_uri(&format!("https://gateway.example/ipfs/{}?download=1", CID)).as_deref(),
+            Some(CID)
+        );
+        assert_eq!(cid_from_uri("ipfs://"), None);
+        assert_eq!(cid_from_uri("https://example.com/metadata.json"), None);
+        assert_eq!(cid_from_uri("data:application/json,{}"), None);
+    }
+
+    #[test]
+    fn decodes_data_uris() {
+        assert_eq!(fetch_uri("data:application/json;base64,eyJhIjoxfQ==").unwrap(), br#"{"a":1}"#);
+        assert_eq!(
+            fetch_uri("data:application/json,%7B%22name%22%3A%22a%20b%22%7D").unwrap(),
+            br#"{"name":"a b"}"#
+        );
+        assert_eq!(
+            fetch_uri("data:text/plain;charset=utf-8,caf%C3%A9").unwrap(),
+            "café".as_bytes()
+        );
+        // Malformed escapes are kept rather than failing the fetch
+        assert_eq!(fetch_uri("data:,100%25 and 100%").unwrap(), b"100% and 100%");
+        assert_eq!(fetch_uri("data:,%zz").unwrap(), b"%zz");
+        assert!(fetch_uri("data:application/json;base64,not base64").is_err());
+        assert!(fetch_uri("data:no comma").is_err());
+    }
+}
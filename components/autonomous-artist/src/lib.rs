@@ -1,23 +1,47 @@
+mod batch;
 #[allow(warnings)]
 mod bindings;
+mod canonical;
 mod evm;
 mod image;
 mod ipfs;
+mod media;
 mod nft;
+mod options;
+mod postprocess;
+mod presets;
+mod procedural;
+mod provenance;
+mod renditions;
+mod svg;
+mod traits;
+mod transcript;
 
-use alloy_primitives::Address;
+use alloy_primitives::{Address, U256};
 use alloy_sol_macro::sol;
 use alloy_sol_types::SolValue;
 use base64;
 use base64::Engine;
+use batch::{token_steps, BatchMint, Budget, Seeds};
 use bindings::{
     export,
-    wavs::worker::layer_types::{TriggerData, TriggerDataEthContractEvent},
+    host::get_eth_chain_config,
+    wavs::worker::layer_types::{EthEventLogData, TriggerData, TriggerDataEthContractEvent},
     Guest, TriggerAction,
 };
-use evm::query_nft_ownership;
-use nft::{Attribute, NFTMetadata};
+use evm::{find_token_uri_event, query_nft_ownership, query_token_uri, verify_log};
+use image::{Backend, SdParams};
+use nft::{Attribute, MediaFile, NFTMetadata, PreviousRevision, Properties};
+use options::MintOptions;
+use postprocess::PostProcessConfig;
+use presets::{PresetRegistry, StylePreset};
+use procedural::ProceduralArt;
+use provenance::{LlmProvenance, Provenance, TriggerOrigin};
+use renditions::{Rendition, RenditionConfig};
 use std::str::FromStr;
+use svg::{OutputMode, SvgConfig};
+use traits::TraitTable;
+use transcript::Transcript;
 use wavs_wasi_chain::decode_event_log_data;
 
 use wavs_llm::{
@@ -32,173 +56,108 @@ use wavs_llm::{
 sol!("../../src/interfaces/IWavsNftServiceTypes.sol");
 
 use crate::IWavsNftServiceTypes::{
-    WavsMintResult, WavsNftTrigger, WavsResponse, WavsTriggerType, WavsUpdateResult,
+    WavsMintResult, WavsNftTrigger, WavsRawTrigger, WavsResponse, WavsTriggerType, WavsUpdateResult,
 };
 struct Component;
 
+/// Everything needed to generate the metadata of one token
+struct TokenRequest<'a> {
+    sender: Address,
+    prompt: &'a str,
+    trigger_id: u64,
+    origin: &'a TriggerOrigin,
+    options: &'a MintOptions,
+    preset: &'a StylePreset,
+    nft_contract: Address,
+    seeds: Seeds,
+    /// Rarity traits, loaded once per trigger
+    trait_table: &'a TraitTable,
+    /// The token whose metadata is replaced, for updates
+    update_of: Option<U256>,
+}
+
 impl Guest for Component {
     /// @dev This function is called when a WAVS trigger action is fired.
     fn run(action: TriggerAction) -> std::result::Result<Option<Vec<u8>>, String> {
         // Decode the trigger event
-        let WavsNftTrigger { sender, prompt, triggerId, wavsTriggerType, tokenId } =
-            match action.data {
-                // Fired from an Ethereum contract event.
-                TriggerData::EthContractEvent(TriggerDataEthContractEvent { log, .. }) => {
-                    decode_event_log_data!(log)
-                        .map_err(|e| format!("Failed to decode event log data: {}", e))
-                }
-                // Fired from a raw data event (e.g. from a CLI command or from another component).
-                TriggerData::Raw(_) => {
-                    unimplemented!("Raw data is not supported yet");
-                }
-                _ => Err("Unsupported trigger data type".to_string()),
-            }?;
+        let (
+            WavsNftTrigger { sender, prompt, triggerId, wavsTriggerType, tokenId, options },
+            origin,
+        ) = match action.data {
+            // Fired from an Ethereum contract event.
+            TriggerData::EthContractEvent(TriggerDataEthContractEvent {
+                log,
+                contract_address,
+                chain_name,
+                block_height,
+            }) => {
+                let event: WavsNftTrigger = decode_event_log_data!(log)
+                    .map_err(|e| format!("Failed to decode event log data: {}", e))?;
+                let origin = TriggerOrigin {
+                    chain_name,
+                    contract: Address::from_slice(&contract_address.raw_bytes),
+                    block_number: block_height,
+                };
+                (event, origin)
+            }
+            // Fired from a raw data event (e.g. from a CLI command or from another component).
+            // The data replays an event log with its origin, so the token comes out as it
+            // would have from the event. The log is read back from the chain, so only events
+            // that were emitted can be replayed.
+            TriggerData::Raw(data) => {
+                let raw = WavsRawTrigger::abi_decode(&data, true)
+                    .map_err(|e| format!("Failed to decode raw trigger: {}", e))?;
+                verify_log(&raw.chainName, raw.emitter, raw.blockNumber, &raw.topics, &raw.data)?;
+                let log = EthEventLogData {
+                    topics: raw.topics.iter().map(|topic| topic.to_vec()).collect(),
+                    data: raw.data.to_vec(),
+                };
+                let event: WavsNftTrigger = decode_event_log_data!(log)
+                    .map_err(|e| format!("Failed to decode event log data: {}", e))?;
+                let origin = TriggerOrigin {
+                    chain_name: raw.chainName,
+                    contract: raw.emitter,
+                    block_number: raw.blockNumber,
+                };
+                (event, origin)
+            }
+            _ => return Err("Unsupported trigger data type".to_string()),
+        };
 
         eprintln!("Processing Trigger ID: {}", triggerId);
         eprintln!("Prompt: {}", &prompt);
 
-        let model = "llama3.2".to_string();
-        let llm_config = LlmOptions {
-            context_window: Some(1024),
-            max_tokens: Some(1024),
-            seed: 42,
-            temperature: 0.7,
-            top_p: 0.9,
+        // A batch carries the mint options of its tokens inside the batch options
+        let batch = match wavsTriggerType {
+            2 => Some(BatchMint::decode(&prompt, &options, batch::max_batch_size()?)?),
+            _ => None,
         };
+        let options = MintOptions::decode(batch.as_ref().map_or(&options[..], |b| &b.options))?;
+        eprintln!("Options: {:?}", options);
 
-        // Create LLM client implementation using the standalone constructor
-        let llm_client = with_config(model.clone(), llm_config).map_err(|e| e.to_string())?;
-
-        let response =
-            llm_client.chat_completion_text(vec![Message {
-                role: "system".to_string(),
-                content: Some("You are avant garde artist and philosopher Gilles Deleuze. Write no more than two sentences about the prompt.".to_string()),
-                tool_calls: None,
-                tool_call_id: None,
-                name: None,
-            }, Message {
-                role: "user".to_string(),
-                content: Some(prompt.clone()),
-                tool_calls: None,
-                tool_call_id: None,
-                name: None,
-            }]).map_err(|e| e.to_string())?;
-
-        eprintln!("Response: {}", response);
-
-        // Check the creator's ETH balance
-        let sender_address = sender.to_string();
-        eprintln!("Checking balance for address: {}", sender_address);
-
-        let mut attributes =
-            vec![Attribute { trait_type: "Prompt".to_string(), value: prompt.clone() }];
+        let registry = PresetRegistry::load()?;
+        let preset = registry.select(options.style_preset_id)?;
+        eprintln!("Style preset: {} ({})", preset.name, preset.id);
 
         // TODO get nft contract address from KV store
         let nft_contract = std::env::var("nft_contract")
             .map_err(|e| format!("Failed to get nft contract: {}", e))?;
         eprintln!("NFT contract: {}", nft_contract);
 
-        // Query NFT balance and add a "wealth" attribute if balance > 1 ETH
-        let owns_nft = query_nft_ownership(sender, Address::from_str(&nft_contract).unwrap())?;
-        if owns_nft {
-            eprintln!("NFT owner: {}", sender);
-            attributes.push(Attribute {
-                trait_type: "Wealth Level".to_string(),
-                value: "Rich".to_string(),
-            });
-        } else {
-            eprintln!("Sender {} does not own NFT", sender);
-            attributes.push(Attribute {
-                trait_type: "Wealth Level".to_string(),
-                value: "Pre-Rich".to_string(),
-            });
-        }
-
-        let title = llm_client.chat_completion_text(vec![Message {
-            role: "system".to_string(),
-            content: Some("You are avant garde artist and philosopher Gilles Deleuze. Write a title for the following text. Use no more than 3 words.".to_string()),
-            tool_calls: None,
-            tool_call_id: None,
-            name: None,
-        }, Message {
-            role: "user".to_string(),
-            content: Some(response.clone()),
-            tool_calls: None,
-            tool_call_id: None,
-            name: None,
-        }]).map_err(|e| e.to_string())?;
-
-        let sd_prompt = llm_client.chat_completion_text(vec![Message {
-            role: "system".to_string(),
-            content: Some("You are an autonomous artist and an expert Stable Diffusion v1.5 prompter. Take the input text and generate a Stable Diffusion prompt. Output ONLY the prompt which will be fed into the Stable Diffusion model txt2img. Use keywords that are relevant to the input text. Make sure the image is square aspect ratio.".to_string()),
-            tool_calls: None,
-            tool_call_id: None,
-            name: None,
-        }, Message {
-            role: "user".to_string(),
-            content: Some(prompt.clone()),
-            tool_calls: None,
-            tool_call_id: None,
-            name: None,
-        }]).map_err(|e| e.to_string())?;
-
-        // Generate image with Stable Diffusion
-        let image_data = image::generate_deterministic_image(&sd_prompt)?;
-
-        // Extract base64 data from data URI
-        let base64_data = image_data
-            .strip_prefix("data:image/png;base64,")
-            .ok_or_else(|| "Invalid image data format".to_string())?;
-
-        // Decode base64 to raw bytes
-        let image_bytes = base64::engine::general_purpose::STANDARD
-            .decode(base64_data)
-            .map_err(|e| format!("Failed to decode base64 image: {}", e))?;
-
-        // Upload image to IPFS first
-        let ipfs_url = std::env::var("WAVS_ENV_IPFS_API_URL")
-            .unwrap_or_else(|_| "https://node.lighthouse.storage/api/v0/add".to_string());
-        let image_uri = match ipfs::upload_nft_content("image/png", &image_bytes, &ipfs_url) {
-            Ok(ipfs_uri) => {
-                eprintln!("Uploaded image to IPFS: {}", ipfs_uri);
-                ipfs_uri
-            }
-            Err(e) => {
-                eprintln!("Failed to upload image to IPFS, falling back to data URI: {}", e);
-                // Fall back to data URI if IPFS upload fails
-                image_data
-            }
-        };
-
-        // Create NFT metadata
-        let metadata = NFTMetadata {
-            name: title,
-            description: response.to_string(),
-            image: image_uri,
-            attributes,
+        let trait_table = TraitTable::load()?;
+        let trigger_seed = traits::trigger_seed(origin.contract, triggerId);
+        let request = TokenRequest {
+            sender,
+            prompt: &prompt,
+            trigger_id: triggerId,
+            origin: &origin,
+            options: &options,
+            preset,
+            nft_contract: Address::from_str(&nft_contract).unwrap(),
+            seeds: Seeds::single(trigger_seed),
+            trait_table: &trait_table,
+            update_of: None,
         };
-        eprintln!("Metadata: {:?}", metadata);
-
-        // Serialize metadata to JSON for IPFS upload
-        let json = serde_json::to_string(&metadata)
-            .map_err(|e| format!("JSON serialization error: {}", e))?;
-
-        // Upload metadata to IPFS
-        let token_uri =
-            match ipfs::upload_nft_content("application/json", json.as_bytes(), &ipfs_url) {
-                Ok(ipfs_uri) => {
-                    eprintln!("Uploaded metadata to IPFS: {}", ipfs_uri);
-                    ipfs_uri
-                }
-                Err(e) => {
-                    eprintln!("Failed to upload to IPFS, falling back to data URI: {}", e);
-                    // Fall back to data URI if IPFS upload fails
-                    format!(
-                        "data:application/json;base64,{}",
-                        base64::engine::general_purpose::STANDARD.encode(json)
-                    )
-                }
-            };
 
         // Create the output based on the trigger type
         let output = match wavsTriggerType {
@@ -208,7 +167,7 @@ impl Guest for Component {
                 data: WavsMintResult {
                     triggerId: triggerId.into(),
                     recipient: sender,
-                    tokenURI: token_uri,
+                    tokenURI: generate_token(&request)?,
                 }
                 .abi_encode()
                 .into(),
@@ -219,7 +178,51 @@ impl Guest for Component {
                 data: WavsUpdateResult {
                     triggerId: triggerId.into(),
                     owner: sender,
-                    tokenURI: token_uri,
+                    tokenURI: generate_token(&TokenRequest {
+                        update_of: Some(tokenId),
+                        ..request
+                    })?,
+                    tokenId,
+                }
+                .abi_encode()
+                .into(),
+            },
+            2 => {
+                let batch = batch.expect("batch options are decoded for BATCH_MINT");
+                let steps = sd_params(0, preset, &options).steps;
+                Budget::from_env()?.check(
+                    batch.prompts.len(),
+                    token_steps(steps, &RenditionConfig::from_env()?),
+                )?;
+                let mut results = Vec::with_capacity(batch.prompts.len());
+                for (index, prompt) in batch.prompts.iter().enumerate() {
+                    eprintln!("Generating batch token {} of {}", index + 1, batch.prompts.len());
+                    let token_uri = generate_token(&TokenRequest {
+                        prompt,
+                        seeds: Seeds::derived(trigger_seed, index),
+                        ..request
+                    })?;
+                    results.push(WavsMintResult {
+                        triggerId: triggerId.into(),
+                        recipient: sender,
+                        tokenURI: token_uri,
+                    });
+                }
+                WavsResponse {
+                    wavsTriggerType: WavsTriggerType::BATCH_MINT,
+                    triggerId,
+                    data: results.abi_encode().into(),
+                }
+            }
+            // The token was minted with the contract's placeholder, generate its art as for a
+            // new mint. The placeholder is not part of the token's revision history.
+            3 => WavsResponse {
+                wavsTriggerType: WavsTriggerType::REVEAL,
+                triggerId,
+                data: WavsUpdateResult {
+                    triggerId: triggerId.into(),
+                    owner: sender,
+                    tokenURI: generate_token(&request)?,
                     tokenId,
                 }
                 .abi_encode()
@@ -232,4 +235,384 @@ impl Guest for Component {
     }
 }
 
+/// Stable Diffusion parameters of a token: the defaults, then the preset, then the options
+fn sd_params(seed: i64, preset: &StylePreset, options: &MintOptions) -> SdParams {
+    let mut params = SdParams { seed, ..SdParams::default() };
+    preset.apply_to(&mut params);
+    options.apply_to(&mut params);
+    params
+}
+
+/// Generate the art and metadata of one token and return its token URI
+fn generate_token(request: &TokenRequest) -> Result<String, String> {
+    let TokenRequest {
+        sender,
+        prompt,
+        trigger_id,
+        origin,
+        options,
+        preset,
+        nft_contract,
+        seeds,
+        trait_table,
+        update_of,
+    } = *request;
+
+    let mut transcript = Transcript::new();
+    transcript.record("prompt", prompt.as_bytes());
+    transcript.record("options", &options.abi_encode());
+    transcript.record(
+        "style_preset",
+        serde_json::to_string(preset)
+            .map_err(|e| format!("JSON serialization error: {}", e))?
+            .as_bytes(),
+    );
+
+    let llm_provenance = LlmProvenance {
+        model: "llama3.2".to_string(),
+        context_window: 1024,
+        max_tokens: 1024,
+        seed: seeds.llm,
+        temperature: 0.7,
+        top_p: 0.9,
+    };
+    let model = llm_provenance.model.clone();
+    let llm_config = LlmOptions {
+        context_window: Some(llm_provenance.context_window),
+        max_tokens: Some(llm_provenance.max_tokens),
+        seed: llm_provenance.seed,
+        // The provenance keeps the decimal values, the client takes single precision
+        temperature: llm_provenance.temperature as f32,
+        top_p: llm_provenance.top_p as f32,
+    };
+
+    // Create LLM client implementation using the standalone constructor
+    let llm_client = with_config(model.clone(), llm_config).map_err(|e| e.to_string())?;
+
+    let response = llm_client
+        .chat_completion_text(vec![
+            Message {
+                role: "system".to_string(),
+                content: Some(format!(
+                    "You are {}. Write no more than two sentences about the prompt.",
+                    preset.persona
+                )),
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+            },
+            Message {
+                role: "user".to_string(),
+                content: Some(prompt.to_string()),
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+            },
+        ])
+        .map_err(|e| e.to_string())?;
+
+    eprintln!("Response: {}", response);
+    transcript.record("description", response.as_bytes());
+
+    // Check the creator's ETH balance
+    let sender_address = sender.to_string();
+    eprintln!("Checking balance for address: {}", sender_address);
+
+    let mut attributes = vec![Attribute::new("Prompt", prompt)];
+
+    // Query NFT balance and add a "wealth" attribute if balance > 1 ETH
+    let owns_nft = query_nft_ownership(sender, nft_contract)?;
+    if owns_nft {
+        eprintln!("NFT owner: {}", sender);
+        attributes.push(Attribute::new("Wealth Level", "Rich"));
+    } else {
+        eprintln!("Sender {} does not own NFT", sender);
+        attributes.push(Attribute::new("Wealth Level", "Pre-Rich"));
+    }
+
+    attributes.extend(preset.attributes());
+    attributes.extend(options.attributes(preset));
+
+    // Draw rarity traits, skipping any the trigger or preset already chose
+    let traits: Vec<_> = trait_table
+        .sample(seeds.traits)
+        .into_iter()
+        .filter(|t| !attributes.iter().any(|a| a.trait_type == t.trait_type))
+        .collect();
+    eprintln!("Traits: {:?}", traits);
+    attributes.extend(traits.iter().map(|t| t.attribute()));
+
+    transcript.record(
+        "attributes",
+        serde_json::to_string(&attributes)
+            .map_err(|e| format!("JSON serialization error: {}", e))?
+            .as_bytes(),
+    );
+
+    let title = llm_client
+        .chat_completion_text(vec![
+            Message {
+                role: "system".to_string(),
+                content: Some(format!(
+                    "You are {}. Write a title for the following text. Use no more than 3 words.",
+                    preset.persona
+                )),
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+            },
+            Message {
+                role: "user".to_string(),
+                content: Some(response.clone()),
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+            },
+        ])
+        .map_err(|e| e.to_string())?;
+
+    let sd_prompt = llm_client.chat_completion_text(vec![Message {
+        role: "system".to_string(),
+        content: Some(format!("You are an autonomous artist and an expert Stable Diffusion v1.5 prompter. Take the input text and generate a Stable Diffusion prompt. Output ONLY the prompt which will be fed into the Stable Diffusion model txt2img. Use keywords that are relevant to the input text. Make sure the image is {} aspect ratio.", options.aspect_ratio.name())),
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+    }, Message {
+        role: "user".to_string(),
+        content: Some(prompt.to_string()),
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+    }]).map_err(|e| e.to_string())?;
+
+    let sd_prompt = format!(
+        "{}{}{}",
+        sd_prompt,
+        options.sd_prompt_suffix(preset),
+        traits::sd_prompt_suffix(&traits)
+    );
+
+    transcript.record("title", title.as_bytes());
+    transcript.record("sd_prompt", sd_prompt.as_bytes());
+
+    let sd_params = sd_params(seeds.sd, preset, options);
+    let art = ProceduralArt::new(prompt, &attributes);
+
+    // Store the image on IPFS, or as an SVG in metadata held by the token URI itself
+    let output_mode = OutputMode::from_env()?;
+    let ipfs_url = std::env::var("WAVS_ENV_IPFS_API_URL")
+        .unwrap_or_else(|_| "https://node.lighthouse.storage/api/v0/add".to_string());
+    let (image_uri, image_data, files, image_backend) = match output_mode {
+        OutputMode::Ipfs => {
+            let (image_uri, files, backend) =
+                publish_image(&sd_prompt, &sd_params, &art, &ipfs_url, &mut transcript)?;
+            let backend = (backend != Backend::StableDiffusion).then(|| backend.name());
+            (image_uri, None, files, backend)
+        }
+        OutputMode::OnChain => {
+            let (svg, source) = svg::generate(
+                &SvgConfig::from_env()?,
+                &sd_prompt,
+                &sd_params,
+                &art,
+                |system, user| {
+                    llm_client
+                        .chat_completion_text(vec![
+                            Message {
+                                role: "system".to_string(),
+                                content: Some(system),
+                                tool_calls: None,
+                                tool_call_id: None,
+                                name: None,
+                            },
+                            Message {
+                                role: "user".to_string(),
+                                content: Some(user.to_string()),
+                                tool_calls: None,
+                                tool_call_id: None,
+                                name: None,
+                            },
+                        ])
+                        .map_err(|e| e.to_string())
+                },
+            )?;
+            eprintln!("Drew a {} byte SVG with {}", svg.len(), source.name());
+            transcript.record("image", svg.as_bytes());
+            (String::new(), Some(svg), Vec::new(), Some(source.name()))
+        }
+    };
+    let animation_url =
+        files.iter().find(|file| file.name == "preview").map(|file| file.uri.clone());
+
+    let chain_id =
+        get_eth_chain_config(&origin.chain_name).map(|config| config.chain_id).unwrap_or_default();
+    let provenance = Provenance::new(
+        chain_id,
+        origin,
+        trigger_id,
+        prompt,
+        llm_provenance,
+        &sd_params,
+        transcript.summary().stages,
+    )
+    .with_image_backend(image_backend);
+
+    // Link updates to the metadata they replace
+    let (revision, previous) = match update_of {
+        Some(token_id) => {
+            // Updates are triggered by the NFT contract, so it lives on the origin chain
+            let previous_uri = query_token_uri(&origin.chain_name, nft_contract, token_id)?;
+            eprintln!("Previous token URI: {}", previous_uri);
+            let previous_json = ipfs::fetch_uri(&previous_uri)
+                .map_err(|e| format!("Failed to fetch previous metadata: {}", e))?;
+            let revision = NFTMetadata::revision_of(&previous_json)? + 1;
+            let previous = if previous_uri.starts_with("data:") {
+                let set_by = find_token_uri_event(
+                    &origin.chain_name,
+                    nft_contract,
+                    token_id,
+                    &previous_uri,
+                )?;
+                PreviousRevision::inline(&previous_json, set_by)?
+            } else {
+                PreviousRevision::linked(previous_uri)
+            };
+            (Some(revision), Some(previous))
+        }
+        None => (None, None),
+    };
+
+    // Create NFT metadata
+    let metadata = NFTMetadata {
+        name: title,
+        description: response.to_string(),
+        image: image_uri,
+        image_data,
+        animation_url,
+        attributes,
+        properties: (!files.is_empty()).then_some(Properties { files }),
+        transcript: transcript::embed_enabled().then(|| transcript.summary()),
+        provenance: Some(provenance),
+        revision,
+        previous,
+        ..Default::default()
+    };
+    eprintln!("Metadata: {:?}", metadata);
+    metadata.validate()?;
+
+    // Serialize metadata to canonical JSON for IPFS upload, so the CID is stable across versions
+    let json = canonical::to_canonical_json(&metadata)?;
+    transcript.record("metadata", json.as_bytes());
+
+    // Upload metadata to IPFS, on-chain art keeps it in the token URI
+    let data_uri = || {
+        format!(
+            "data:application/json;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(&json)
+        )
+    };
+    let token_uri = match output_mode {
+        OutputMode::OnChain => data_uri(),
+        OutputMode::Ipfs => {
+            match ipfs::upload_nft_content("application/json", json.as_bytes(), &ipfs_url) {
+                Ok(ipfs_uri) => {
+                    eprintln!("Uploaded metadata to IPFS: {}", ipfs_uri);
+                    ipfs_uri
+                }
+                Err(e) => {
+                    eprintln!("Failed to upload to IPFS, falling back to data URI: {}", e);
+                    // Fall back to data URI if IPFS upload fails
+                    data_uri()
+                }
+            }
+        }
+    };
+
+    transcript.record("token_uri", token_uri.as_bytes());
+    eprintln!("Transcript digest: {}", transcript.digest());
+
+    Ok(token_uri)
+}
+
+/// Draw the raster image and its renditions and upload them to IPFS, returning the image
+/// URI, the renditions uploaded and the backend that drew the image
+fn publish_image(
+    sd_prompt: &str,
+    sd_params: &SdParams,
+    art: &ProceduralArt,
+    ipfs_url: &str,
+    transcript: &mut Transcript,
+) -> Result<(String, Vec<MediaFile>, Backend), String> {
+    // Generate image with Stable Diffusion, or the procedural backend when configured
+    let (image_bytes, backend) = image::generate(sd_prompt, sd_params, art, &Backend::from_env()?)?;
+    eprintln!("Generated image with {}", backend.name());
+    transcript.record("image", &image_bytes);
+
+    // Validate, resize and watermark the image, dropping the metadata the SD server embedded
+    let post = PostProcessConfig::from_env()?;
+    let image_bytes = postprocess::clean(&image_bytes, sd_params.width, sd_params.height, &post)?;
+
+    // Derive the thumbnail, upscale and animated preview, then encode them in the output format
+    let (main, extras) = renditions::produce(
+        image_bytes,
+        sd_prompt,
+        sd_params,
+        art,
+        backend,
+        &RenditionConfig::from_env()?,
+        &post,
+    )?;
+    let main = postprocess::reencode(main, post.format)?;
+    let extras = extras
+        .into_iter()
+        .map(|rendition| postprocess::reencode(rendition, post.format))
+        .collect::<Result<Vec<_>, _>>()?;
+    for rendition in std::iter::once(&main).chain(&extras) {
+        transcript.record(&format!("rendition_{}", rendition.name), &rendition.bytes);
+    }
+
+    // Upload image to IPFS first, under the MIME type detected from its bytes
+    let max_image_bytes = media::max_image_bytes()?;
+    let content_type = media::verify(&main.bytes, max_image_bytes)?.content_type();
+    let image_uri = upload_or_inline(&main, content_type, ipfs_url);
+
+    // Every rendition is listed, so the metadata doesn't depend on which uploads succeeded
+    let files = extras
+        .iter()
+        .map(|rendition| {
+            let content_type = media::verify(&rendition.bytes, max_image_bytes)?.content_type();
+            Ok(MediaFile {
+                uri: upload_or_inline(rendition, content_type, ipfs_url),
+                content_type: content_type.to_string(),
+                name: rendition.name.to_string(),
+                width: rendition.width,
+                height: rendition.height,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    transcript.record("image_uri", image_uri.as_bytes());
+    Ok((image_uri, files, backend))
+}
+
+/// Upload a rendition to IPFS, falling back to a data URI when the upload fails
+fn upload_or_inline(rendition: &Rendition, content_type: &str, ipfs_url: &str) -> String {
+    match ipfs::upload_nft_content(content_type, &rendition.bytes, ipfs_url) {
+        Ok(ipfs_uri) => {
+            eprintln!("Uploaded {} rendition to IPFS: {}", rendition.name, ipfs_uri);
+            ipfs_uri
+        }
+        Err(e) => {
+            eprintln!(
+                "Failed to upload {} rendition to IPFS, falling back to data URI: {}",
+                rendition.name, e
+            );
+            format!(
+                "data:{};base64,{}",
+                content_type,
+                base64::engine::general_purpose::STANDARD.encode(&rendition.bytes)
+            )
+        }
+    }
+}
+
 export!(Component with_types_in bindings);
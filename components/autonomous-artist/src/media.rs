@@ -0,0 +1,152 @@
+use ::image::ImageFormat;
+
+/// Largest image accepted from the Stable Diffusion server or uploaded, unless configured
+const DEFAULT_MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+
+/// Image formats the component handles, detected from their magic bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaType {
+    Png,
+    Jpeg,
+    WebP,
+    Gif,
+}
+
+impl MediaType {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            MediaType::Png => "image/png",
+            MediaType::Jpeg => "image/jpeg",
+            MediaType::WebP => "image/webp",
+            MediaType::Gif => "image/gif",
+        }
+    }
+
+    pub fn image_format(&self) -> ImageFormat {
+        match self {
+            MediaType::Png => ImageFormat::Png,
+            MediaType::Jpeg => ImageFormat::Jpeg,
+            MediaType::WebP => ImageFormat::WebP,
+            MediaType::Gif => ImageFormat::Gif,
+        }
+    }
+}
+
+/// Detect the format of an image from its magic bytes
+pub fn sniff(bytes: &[u8]) -> Result<MediaType, String> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Ok(MediaType::Png)
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Ok(MediaType::Jpeg)
+    } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        Ok(MediaType::WebP)
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Ok(MediaType::Gif)
+    } else {
+        let head = &bytes[..bytes.len().min(8)];
+        Err(format!("Unrecognized image format, starting with 0x{}", hex::encode(head)))
+    }
+}
+
+/// Check an image is not empty, fits in `max_bytes` and is complete, returning its format.
+///
+/// Completeness is checked on the container's trailer, which catches responses
+/// cut off in transit without decoding the whole image.
+pub fn verify(bytes: &[u8], max_bytes: usize) -> Result<MediaType, String> {
+    if bytes.is_empty() {
+        return Err("Image is empty".to_string());
+    }
+    if bytes.len() > max_bytes {
+        return Err(format!("Image of {} bytes exceeds the limit of {}", bytes.len(), max_bytes));
+    }
+
+    let media_type = sniff(bytes)?;
+    let complete = match media_type {
+        // The last chunk is an empty IEND followed by its CRC
+        MediaType::Png => bytes.ends_with(b"\0\0\0\0IEND\xAE\x42\x60\x82"),
+        // End of image marker
+        MediaType::Jpeg => bytes.ends_with(&[0xFF, 0xD9]),
+        // The RIFF header holds the size of the rest of the file
+        MediaType::WebP => {
+            let size = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+            size.checked_add(8).is_some_and(|total| bytes.len() >= total)
+        }
+        // Trailer byte
+        MediaType::Gif => bytes.ends_with(&[0x3B]),
+    };
+    if !complete {
+        return Err(format!(
+            "Image is truncated, {} bytes of {}",
+            bytes.len(),
+            media_type.content_type()
+        ));
+    }
+    Ok(media_type)
+}
+
+/// Largest image size in bytes, `WAVS_ENV_MAX_IMAGE_BYTES` overrides the 10 MiB default
+pub fn max_image_bytes() -> Result<usize, String> {
+    match std::env::var("WAVS_ENV_MAX_IMAGE_BYTES") {
+        Ok(size) => size.parse().map_err(|e| format!("Invalid WAVS_ENV_MAX_IMAGE_BYTES: {}", e)),
+        Err(_) => Ok(DEFAULT_MAX_IMAGE_BYTES),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\0IEND\xAE\x42\x60\x82";
+    const JPEG: &[u8] = &[0xFF, 0xD8, 0xFF, 0xE0, 0xFF, 0xD9];
+    const GIF: &[u8] = b"GIF89a\x01\x00\x01\x00\x00\x00\x00;";
+    /// RIFF header of 8 bytes followed by the 12 bytes its size field declares
+    const WEBP: &[u8] = b"RIFF\x0c\0\0\0WEBPVP8L\0\0\0\0";
+
+    #[test]
+    fn sniffs_formats() {
+        assert_eq!(sniff(PNG), Ok(MediaType::Png));
+        assert_eq!(sniff(JPEG), Ok(MediaType::Jpeg));
+        assert_eq!(sniff(WEBP), Ok(MediaType::WebP));
+        assert_eq!(sniff(GIF), Ok(MediaType::Gif));
+        assert_eq!(
+            sniff(b"<svg xmlns").unwrap_err(),
+            "Unrecognized image format, starting with 0x3c73766720786d6c"
+        );
+        assert!(sniff(b"RIFF").is_err());
+        assert!(sniff(&[]).is_err());
+    }
+
+    #[test]
+    fn verifies_complete_images() {
+        for (bytes, media_type) in [
+            (PNG, MediaType::Png),
+            (JPEG, MediaType::Jpeg),
+            (WEBP, MediaType::WebP),
+            (GIF, MediaType::Gif),
+        ] {
+            assert_eq!(verify(bytes, 1024), Ok(media_type));
+            assert_eq!(
+                verify(&bytes[..bytes.len() - 1], 1024).unwrap_err(),
+                format!(
+                    "Image is truncated, {} bytes of {}",
+                    bytes.len() - 1,
+                    media_type.content_type()
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_empty_and_oversized_images() {
+        assert_eq!(verify(&[], 1024).unwrap_err(), "Image is empty");
+        assert_eq!(verify(PNG, 4).unwrap_err(), "Image of 20 bytes exceeds the limit of 4");
+        assert_eq!(verify(PNG, PNG.len()), Ok(MediaType::Png));
+    }
+
+    #[test]
+    fn rejects_webp_sizes_past_the_end() {
+        let mut webp = WEBP.to_vec();
+        webp[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(verify(&webp, 1024).unwrap_err().starts_with("Image is truncated"));
+    }
+}
@@ -1,16 +1,487 @@
-use serde::Serialize;
+use crate::canonical::{self, MAX_EXACT_INTEGER};
+use crate::ipfs;
+use crate::provenance::Provenance;
+use crate::transcript::TranscriptSummary;
+use alloy_primitives::keccak256;
+use serde::{Deserialize, Serialize};
+use url::Url;
 
-// NFT Metadata structure
-#[derive(Serialize, Debug)]
+/// URL schemes accepted for links in the metadata
+const URL_SCHEMES: [&str; 5] = ["ipfs", "https", "http", "ar", "data"];
+
+// NFT Metadata structure, following the ERC-721 metadata JSON schema and the
+// OpenSea metadata standard (https://docs.opensea.io/docs/metadata-standards)
+#[derive(Serialize, Debug, Default)]
 pub struct NFTMetadata {
     pub name: String,
     pub description: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
     pub image: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_data: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub animation_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub youtube_url: Option<String>,
+    /// Six character hexadecimal color, without a leading `#`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_color: Option<String>,
     pub attributes: Vec<Attribute>,
+    /// Additional renditions of the image
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub properties: Option<Properties>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcript: Option<TranscriptSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+    /// Number of updates since the original mint, absent for the original mint
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revision: Option<u64>,
+    /// The metadata this revision replaced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous: Option<PreviousRevision>,
+}
+
+/// Link to the prior metadata of an updated token, forming a history chain
+/// that can be walked back to the original mint
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PreviousRevision {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cid: Option<String>,
+    /// keccak256 of the canonical JSON of prior metadata kept in a data URI
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    /// The event that set prior metadata kept in a data URI, its `tokenURI` holds the metadata
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub set_by: Option<LogLocation>,
+}
+
+/// Where an event log was emitted, on the chain of the token
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LogLocation {
+    pub block_number: u64,
+    pub transaction_hash: String,
+    pub log_index: u64,
+}
+
+impl PreviousRevision {
+    /// Link to metadata stored at `token_uri`, such as on IPFS
+    pub fn linked(token_uri: String) -> Self {
+        let cid = ipfs::cid_from_uri(&token_uri);
+        PreviousRevision { token_uri: Some(token_uri), cid, digest: None, set_by: None }
+    }
+
+    /// Link to metadata `json` kept in a data URI, through the event that set it.
+    ///
+    /// Embedding the URI would nest every earlier revision in each update and grow the
+    /// token URI without bound. The event's `tokenURI` resolves to the metadata instead,
+    /// and the digest checks it.
+    pub fn inline(json: &[u8], set_by: LogLocation) -> Result<Self, String> {
+        let previous: serde_json::Value = serde_json::from_slice(json)
+            .map_err(|e| format!("Failed to parse existing metadata: {}", e))?;
+        let canonical = canonical::to_canonical_json(&previous)?;
+        Ok(PreviousRevision {
+            token_uri: None,
+            cid: None,
+            digest: Some(keccak256(canonical.as_bytes()).to_string()),
+            set_by: Some(set_by),
+        })
+    }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct Properties {
+    pub files: Vec<MediaFile>,
+}
+
+/// A file of the token, as listed in `properties.files`
+#[derive(Serialize, Debug, Clone)]
+pub struct MediaFile {
+    pub uri: String,
+    /// MIME type of the file
+    #[serde(rename = "type")]
+    pub content_type: String,
+    /// Role of the rendition, e.g. "thumbnail"
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Attribute {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_type: Option<DisplayType>,
     pub trait_type: String,
-    pub value: String,
+    pub value: AttributeValue,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_value: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum AttributeValue {
+    Text(String),
+    Number(f64),
+}
+
+/// How marketplaces render a numeric attribute
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplayType {
+    Number,
+    BoostPercentage,
+    BoostNumber,
+    /// Unix timestamp in seconds
+    Date,
+}
+
+impl From<String> for AttributeValue {
+    fn from(value: String) -> Self {
+        AttributeValue::Text(value)
+    }
+}
+
+impl From<&str> for AttributeValue {
+    fn from(value: &str) -> Self {
+        AttributeValue::Text(value.to_string())
+    }
+}
+
+impl From<f64> for AttributeValue {
+    fn from(value: f64) -> Self {
+        AttributeValue::Number(value)
+    }
+}
+
+impl From<u32> for AttributeValue {
+    fn from(value: u32) -> Self {
+        AttributeValue::Number(value.into())
+    }
+}
+
+/// Only integers up to 2^53 are exact as a JSON number, larger ones should be text
+impl TryFrom<u64> for AttributeValue {
+    type Error = String;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        if value > MAX_EXACT_INTEGER {
+            return Err(format!("Attribute value {} cannot be represented exactly", value));
+        }
+        Ok(AttributeValue::Number(value as f64))
+    }
+}
+
+impl Attribute {
+    pub fn new(trait_type: impl Into<String>, value: impl Into<AttributeValue>) -> Self {
+        Attribute {
+            display_type: None,
+            trait_type: trait_type.into(),
+            value: value.into(),
+            max_value: None,
+        }
+    }
+
+    pub fn with_display_type(mut self, display_type: DisplayType) -> Self {
+        self.display_type = Some(display_type);
+        self
+    }
+
+    pub fn with_max_value(mut self, max_value: f64) -> Self {
+        self.max_value = Some(max_value);
+        self
+    }
+
+    /// Check the attribute is well formed for its display type
+    pub fn validate(&self) -> Result<(), String> {
+        if self.trait_type.trim().is_empty() {
+            return Err("Attribute trait_type must not be empty".to_string());
+        }
+
+        let number = match self.value {
+            AttributeValue::Number(n) if !n.is_finite() => {
+                return Err(format!("Attribute {} has a non-finite value", self.trait_type));
+            }
+            AttributeValue::Number(n) => Some(n),
+            AttributeValue::Text(_) => None,
+        };
+
+        if let Some(display_type) = self.display_type {
+            let n = number.ok_or_else(|| {
+                format!(
+                    "Attribute {} with display_type {:?} must have a numeric value",
+                    self.trait_type, display_type
+                )
+            })?;
+            if display_type == DisplayType::Date && (n < 0.0 || n.fract() != 0.0) {
+                return Err(format!(
+                    "Attribute {} must be a unix timestamp in seconds",
+                    self.trait_type
+                ));
+            }
+        }
+
+        if let Some(max_value) = self.max_value {
+            let n = number.ok_or_else(|| {
+                format!("Attribute {} with max_value must have a numeric value", self.trait_type)
+            })?;
+            if self.display_type == Some(DisplayType::Date) {
+                return Err(format!(
+                    "Attribute {} with display_type date cannot have a max_value",
+                    self.trait_type
+                ));
+            }
+            if !max_value.is_finite() || n > max_value {
+                return Err(format!(
+                    "Attribute {} value {} exceeds max_value {}",
+                    self.trait_type, n, max_value
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl NFTMetadata {
+    /// Read the revision of existing metadata, metadata without one is the original mint
+    pub fn revision_of(json: &[u8]) -> Result<u64, String> {
+        #[derive(Deserialize)]
+        struct Revision {
+            #[serde(default)]
+            revision: Option<u64>,
+        }
+
+        let existing: Revision = serde_json::from_slice(json)
+            .map_err(|e| format!("Failed to parse existing metadata: {}", e))?;
+        Ok(existing.revision.unwrap_or(0))
+    }
+
+    /// Reject malformed metadata before it is uploaded
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Metadata name must not be empty".to_string());
+        }
+
+        if self.image.is_empty() && self.image_data.is_none() {
+            return Err("Metadata must have an image or image_data".to_string());
+        }
+        if !self.image.is_empty() {
+            validate_url("image", &self.image)?;
+        }
+
+        if let Some(image_data) = &self.image_data {
+            let image_data = image_data.trim_start();
+            if !image_data.starts_with("<svg") && !image_data.starts_with("<?xml") {
+                return Err("Metadata image_data must be a raw SVG document".to_string());
+            }
+        }
+
+        for (field, value) in [
+            ("external_url", &self.external_url),
+            ("animation_url", &self.animation_url),
+            ("youtube_url", &self.youtube_url),
+        ] {
+            if let Some(value) = value {
+                validate_url(field, value)?;
+            }
+        }
+
+        if let Some(properties) = &self.properties {
+            for file in &properties.files {
+                validate_url("properties.files", &file.uri)?;
+            }
+        }
+
+        if let Some(color) = &self.background_color {
+            if color.len() != 6 || !color.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!(
+                    "Metadata background_color must be six hexadecimal characters without '#', got {}",
+                    color
+                ));
+            }
+        }
+
+        for attribute in &self.attributes {
+            attribute.validate()?;
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_url(field: &str, value: &str) -> Result<(), String> {
+    let url =
+        Url::parse(value).map_err(|e| format!("Metadata {} is not a valid URL: {}", field, e))?;
+    if !URL_SCHEMES.contains(&url.scheme()) {
+        return Err(format!("Metadata {} has unsupported scheme {}", field, url.scheme()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata() -> NFTMetadata {
+        NFTMetadata {
+            name: "Lighthouse".to_string(),
+            image: "ipfs://bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn rejects(metadata: NFTMetadata, error: &str) {
+        let result = metadata.validate();
+        assert!(
+            result.as_ref().is_err_and(|e| e.contains(error)),
+            "expected an error containing {:?}, got {:?}",
+            error,
+            result
+        );
+    }
+
+    #[test]
+    fn accepts_well_formed_metadata() {
+        let metadata = NFTMetadata {
+            image_data: Some("<svg xmlns='http://www.w3.org/2000/svg'/>".to_string()),
+            external_url: Some("https://example.com/token/1".to_string()),
+            background_color: Some("1a2B3c".to_string()),
+            attributes: vec![
+                Attribute::new("Generation", 2u32)
+                    .with_display_type(DisplayType::Number)
+                    .with_max_value(10.0),
+                Attribute::new("Minted", 1_700_000_000u32).with_display_type(DisplayType::Date),
+            ],
+            ..metadata()
+        };
+        assert_eq!(metadata.validate(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_missing_fields() {
+        rejects(NFTMetadata { name: " ".to_string(), ..metadata() }, "name must not be empty");
+        rejects(NFTMetadata { image: String::new(), ..metadata() }, "an image or image_data");
+        rejects(
+            NFTMetadata { image_data: Some("<html></html>".to_string()), ..metadata() },
+            "raw SVG document",
+        );
+    }
+
+    #[test]
+    fn rejects_bad_urls() {
+        rejects(NFTMetadata { image: "not a url".to_string(), ..metadata() }, "not a valid URL");
+        rejects(
+            NFTMetadata { animation_url: Some("file:///etc/passwd".to_string()), ..metadata() },
+            "unsupported scheme file",
+        );
+        let properties = Properties {
+            files: vec![MediaFile {
+                uri: "ftp://example.com/thumb.png".to_string(),
+                content_type: "image/png".to_string(),
+                name: "thumbnail".to_string(),
+                width: 256,
+                height: 256,
+            }],
+        };
+        rejects(NFTMetadata { properties: Some(properties), ..metadata() }, "properties.files");
+        rejects(
+            NFTMetadata { background_color: Some("#ffffff".to_string()), ..metadata() },
+            "background_color",
+        );
+    }
+
+    #[test]
+    fn rejects_bad_attributes() {
+        let with = |attribute: Attribute| NFTMetadata { attributes: vec![attribute], ..metadata() };
+
+        rejects(with(Attribute::new(" ", "Rich")), "trait_type must not be empty");
+        rejects(with(Attribute::new("Level", f64::NAN)), "non-finite value");
+        rejects(with(Attribute::new("Level", f64::INFINITY)), "non-finite value");
+        rejects(
+            with(Attribute::new("Level", "high").with_display_type(DisplayType::BoostNumber)),
+            "must have a numeric value",
+        );
+        rejects(
+            with(Attribute::new("Minted", -1.0).with_display_type(DisplayType::Date)),
+            "unix timestamp",
+        );
+        rejects(
+            with(Attribute::new("Minted", 1.5).with_display_type(DisplayType::Date)),
+            "unix timestamp",
+        );
+        rejects(
+            with(
+                Attribute::new("Minted", 1_700_000_000u32)
+                    .with_display_type(DisplayType::Date)
+                    .with_max_value(2e9),
+            ),
+            "cannot have a max_value",
+        );
+        rejects(with(Attribute::new("Level", "high").with_max_value(10.0)), "numeric value");
+        rejects(with(Attribute::new("Level", 11u32).with_max_value(10.0)), "exceeds max_value");
+        rejects(with(Attribute::new("Level", 1u32).with_max_value(f64::NAN)), "exceeds max_value");
+    }
+
+    #[test]
+    fn reads_the_revision_of_existing_metadata() {
+        assert_eq!(NFTMetadata::revision_of(br#"{"name":"Lighthouse"}"#), Ok(0));
+        assert_eq!(NFTMetadata::revision_of(br#"{"name":"Lighthouse","revision":3}"#), Ok(3));
+        assert_eq!(NFTMetadata::revision_of(br#"{"revision":null}"#), Ok(0));
+        assert!(NFTMetadata::revision_of(br#"{"revision":"3"}"#).is_err());
+        assert!(NFTMetadata::revision_of(b"not json").is_err());
+    }
+
+    #[test]
+    fn links_previous_revisions() {
+        let uri =
+            "ipfs://bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku/nft_metadata.json";
+        assert_eq!(
+            PreviousRevision::linked(uri.to_string()),
+            PreviousRevision {
+                token_uri: Some(uri.to_string()),
+                cid: Some(
+                    "bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku".to_string()
+                ),
+                digest: None,
+                set_by: None,
+            }
+        );
+
+        // Keys are sorted before hashing, so the digest is that of the canonical form
+        let json = br#"{"revision":1,"name":"Lighthouse"}"#;
+        let set_by = LogLocation {
+            block_number: 42,
+            transaction_hash: keccak256(b"update tx").to_string(),
+            log_index: 3,
+        };
+        let previous = PreviousRevision::inline(json, set_by.clone()).unwrap();
+        let digest = keccak256(br#"{"name":"Lighthouse","revision":1}"#).to_string();
+        assert_eq!(
+            previous,
+            PreviousRevision {
+                token_uri: None,
+                cid: None,
+                digest: Some(digest.clone()),
+                set_by: Some(set_by.clone())
+            }
+        );
+        assert_eq!(
+            canonical::to_canonical_json(&previous).unwrap(),
+            format!(
+                r#"{{"digest":"{}","set_by":{{"block_number":42,"log_index":3,"transaction_hash":"{}"}}}}"#,
+                digest, set_by.transaction_hash
+            )
+        );
+        assert!(PreviousRevision::inline(b"not json", set_by).is_err());
+    }
+
+    #[test]
+    fn keeps_large_integers_exact() {
+        assert_eq!(AttributeValue::try_from(1u64 << 53), Ok(AttributeValue::Number(2f64.powi(53))));
+        assert!(AttributeValue::try_from((1u64 << 53) + 1).is_err());
+    }
 }
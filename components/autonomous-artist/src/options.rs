@@ -0,0 +1,240 @@
+use crate::image::SdParams;
+use crate::nft::Attribute;
+use crate::presets::StylePreset;
+use crate::IWavsNftServiceTypes::{WavsAspectRatio, WavsMintOptions};
+use alloy_sol_types::SolValue;
+
+/// Color palettes selectable with `palette`
+pub const PALETTES: [&str; 6] = ["vivid", "pastel", "monochrome", "earth", "neon", "sepia"];
+
+/// Maximum length of the negative prompt additions, in characters
+pub const MAX_NEGATIVE_PROMPT_LEN: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AspectRatio {
+    #[default]
+    Square,
+    Portrait,
+    Landscape,
+}
+
+impl AspectRatio {
+    /// Image size in pixels, as (width, height)
+    pub fn dimensions(&self) -> (u32, u32) {
+        match self {
+            AspectRatio::Square => (512, 512),
+            AspectRatio::Portrait => (512, 768),
+            AspectRatio::Landscape => (768, 512),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AspectRatio::Square => "square",
+            AspectRatio::Portrait => "portrait",
+            AspectRatio::Landscape => "landscape",
+        }
+    }
+}
+
+/// Mint-time parameters of a trigger, validated against the allow-lists above
+#[derive(Debug, Clone, Default)]
+pub struct MintOptions {
+    /// Resolved against the `PresetRegistry`, 0 selects the collection default
+    pub style_preset_id: u16,
+    pub aspect_ratio: AspectRatio,
+    pub negative_prompt: String,
+    pub palette: Option<String>,
+}
+
+impl MintOptions {
+    /// Decode ABI-encoded `WavsMintOptions`, empty options use the defaults
+    pub fn decode(options: &[u8]) -> Result<Self, String> {
+        if options.is_empty() {
+            return Ok(Self::default());
+        }
+
+        let WavsMintOptions { stylePresetId, aspectRatio, negativePrompt, palette } =
+            WavsMintOptions::abi_decode(options, true)
+                .map_err(|e| format!("Failed to decode mint options: {}", e))?;
+
+        let aspect_ratio = match aspectRatio {
+            WavsAspectRatio::SQUARE => AspectRatio::Square,
+            WavsAspectRatio::PORTRAIT => AspectRatio::Portrait,
+            WavsAspectRatio::LANDSCAPE => AspectRatio::Landscape,
+            _ => return Err("Invalid aspect ratio".to_string()),
+        };
+
+        let options = MintOptions {
+            style_preset_id: stylePresetId,
+            aspect_ratio,
+            negative_prompt: negativePrompt.trim().to_string(),
+            palette: (!palette.is_empty()).then(|| palette.to_lowercase()),
+        };
+        options.validate()?;
+        Ok(options)
+    }
+
+    /// ABI-encode the options as `WavsMintOptions`, after normalization, so omitted and
+    /// explicit default options encode the same
+    pub fn abi_encode(&self) -> Vec<u8> {
+        WavsMintOptions {
+            stylePresetId: self.style_preset_id,
+            aspectRatio: match self.aspect_ratio {
+                AspectRatio::Square => WavsAspectRatio::SQUARE,
+                AspectRatio::Portrait => WavsAspectRatio::PORTRAIT,
+                AspectRatio::Landscape => WavsAspectRatio::LANDSCAPE,
+            },
+            negativePrompt: self.negative_prompt.clone(),
+            palette: self.palette.clone().unwrap_or_default(),
+        }
+        .abi_encode()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.negative_prompt.chars().count() > MAX_NEGATIVE_PROMPT_LEN {
+            return Err(format!(
+                "Negative prompt additions exceed {} characters",
+                MAX_NEGATIVE_PROMPT_LEN
+            ));
+        }
+        // Keep users from smuggling instructions or prompt syntax into the negative prompt
+        if !self.negative_prompt.chars().all(|c| c.is_alphanumeric() || " ,-'".contains(c)) {
+            return Err(
+                "Negative prompt additions may only contain letters, digits, spaces, commas, hyphens and apostrophes"
+                    .to_string(),
+            );
+        }
+
+        if let Some(palette) = &self.palette {
+            if !PALETTES.contains(&palette.as_str()) {
+                return Err(format!("Unknown palette: {}", palette));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The trigger's palette, or the preset's when the trigger does not choose one
+    pub fn palette<'a>(&'a self, preset: &'a StylePreset) -> Option<&'a str> {
+        self.palette.as_deref().or(preset.palette.as_deref())
+    }
+
+    /// Style keywords and palette appended to the Stable Diffusion prompt
+    pub fn sd_prompt_suffix(&self, preset: &StylePreset) -> String {
+        let mut suffix = String::new();
+        if !preset.keywords.is_empty() {
+            suffix.push_str(", ");
+            suffix.push_str(&preset.keywords);
+        }
+        if let Some(palette) = self.palette(preset) {
+            suffix.push_str(&format!(", {} color palette", palette));
+        }
+        suffix
+    }
+
+    /// Apply the image size and negative prompt additions to the generation parameters,
+    /// after the preset has been applied
+    pub fn apply_to(&self, params: &mut SdParams) {
+        let (width, height) = self.aspect_ratio.dimensions();
+        params.width = width;
+        params.height = height;
+
+        if self.aspect_ratio != AspectRatio::Square {
+            params.negative_prompt = params
+                .negative_prompt
+                .split(", ")
+                .filter(|term| *term != "nonsquare")
+                .collect::<Vec<_>>()
+                .join(", ");
+        }
+        if !self.negative_prompt.is_empty() {
+            params.negative_prompt =
+                format!("{}, {}", params.negative_prompt, self.negative_prompt);
+        }
+    }
+
+    /// Attributes recording the options that differ from the defaults
+    pub fn attributes(&self, preset: &StylePreset) -> Vec<Attribute> {
+        let mut attributes = Vec::new();
+        if self.aspect_ratio != AspectRatio::Square {
+            attributes.push(Attribute::new("Aspect Ratio", self.aspect_ratio.name()));
+        }
+        if let Some(palette) = self.palette(preset) {
+            attributes.push(Attribute::new("Palette", palette));
+        }
+        attributes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(aspect_ratio: WavsAspectRatio, negative_prompt: &str, palette: &str) -> Vec<u8> {
+        WavsMintOptions {
+            stylePresetId: 2,
+            aspectRatio: aspect_ratio,
+            negativePrompt: negative_prompt.to_string(),
+            palette: palette.to_string(),
+        }
+        .abi_encode()
+    }
+
+    #[test]
+    fn decodes_and_normalizes_options() {
+        let options =
+            MintOptions::decode(&encode(WavsAspectRatio::PORTRAIT, "  text, watermark ", "Pastel"))
+                .unwrap();
+        assert_eq!(options.style_preset_id, 2);
+        assert_eq!(options.aspect_ratio, AspectRatio::Portrait);
+        assert_eq!(options.negative_prompt, "text, watermark");
+        assert_eq!(options.palette.as_deref(), Some("pastel"));
+        assert_eq!(
+            options.abi_encode(),
+            encode(WavsAspectRatio::PORTRAIT, "text, watermark", "pastel")
+        );
+
+        let defaults = MintOptions::decode(&[]).unwrap();
+        assert_eq!(defaults.aspect_ratio, AspectRatio::Square);
+        assert_eq!(defaults.palette, None);
+        assert_eq!(
+            defaults.abi_encode(),
+            MintOptions::decode(&defaults.abi_encode()).unwrap().abi_encode()
+        );
+    }
+
+    #[test]
+    fn enforces_the_palette_allow_list() {
+        for palette in PALETTES {
+            assert!(MintOptions::decode(&encode(WavsAspectRatio::SQUARE, "", palette)).is_ok());
+        }
+        let error =
+            MintOptions::decode(&encode(WavsAspectRatio::SQUARE, "", "rainbow")).unwrap_err();
+        assert_eq!(error, "Unknown palette: rainbow");
+    }
+
+    #[test]
+    fn rejects_bad_enum_values() {
+        // The aspect ratio follows the offset of the struct and the preset id
+        let mut options = encode(WavsAspectRatio::SQUARE, "", "");
+        options[95] = 3;
+        assert!(MintOptions::decode(&options).is_err());
+        assert!(MintOptions::decode(&[0x01, 0x02]).is_err());
+    }
+
+    #[test]
+    fn limits_the_negative_prompt() {
+        let longest = "a".repeat(MAX_NEGATIVE_PROMPT_LEN);
+        assert!(MintOptions::decode(&encode(WavsAspectRatio::SQUARE, &longest, "")).is_ok());
+
+        let oversized = "a".repeat(MAX_NEGATIVE_PROMPT_LEN + 1);
+        let error =
+            MintOptions::decode(&encode(WavsAspectRatio::SQUARE, &oversized, "")).unwrap_err();
+        assert!(error.contains("exceed"));
+
+        for smuggled in ["(masterpiece:1.5)", "ignore [this]", "a\nb", "a; b"] {
+            assert!(MintOptions::decode(&encode(WavsAspectRatio::SQUARE, smuggled, "")).is_err());
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use crate::ipfs;
+use crate::media::{self, MediaType};
+use crate::renditions::Rendition;
+use ::image::codecs::jpeg::JpegEncoder;
+use ::image::codecs::webp::WebPEncoder;
+use ::image::imageops::{self, FilterType};
+use ::image::{DynamicImage, ImageFormat, RgbaImage};
+use std::io::Cursor;
+
+/// Encoding of the uploaded images
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Png,
+    /// Lossless WebP, the pure-Rust encoder has no lossy mode
+    WebP,
+    /// JPEG at the given quality, 1 to 100
+    Jpeg(u8),
+}
+
+/// A collection watermark stamped in the bottom right corner
+#[derive(Debug, Clone)]
+pub struct Watermark {
+    pub image: RgbaImage,
+    /// Opacity from 0 to 100, applied on top of the watermark's own alpha
+    pub opacity: u8,
+}
+
+/// Post-processing applied to every generated image, read from the environment
+#[derive(Debug, Clone)]
+pub struct PostProcessConfig {
+    pub format: OutputFormat,
+    pub watermark: Option<Watermark>,
+}
+
+impl PostProcessConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let quality = std::env::var("WAVS_ENV_IMAGE_QUALITY").ok();
+        let format = match std::env::var("WAVS_ENV_IMAGE_FORMAT").as_deref() {
+            Err(_) | Ok("png") => OutputFormat::Png,
+            Ok("webp") => OutputFormat::WebP,
+            Ok("jpeg") | Ok("jpg") => OutputFormat::Jpeg(jpeg_quality(quality.as_deref())?),
+            Ok(other) => return Err(format!("Unsupported WAVS_ENV_IMAGE_FORMAT: {}", other)),
+        };
+        // PNG and the pure-Rust WebP encoder are lossless, a quality would be ignored
+        if quality.is_some() && !matches!(format, OutputFormat::Jpeg(_)) {
+            return Err(
+                "WAVS_ENV_IMAGE_QUALITY only applies to WAVS_ENV_IMAGE_FORMAT jpeg".to_string()
+            );
+        }
+
+        let watermark = match std::env::var("WAVS_ENV_WATERMARK") {
+            Ok(uri) if !uri.trim().is_empty() => {
+                let bytes = ipfs::fetch_uri(&uri)
+                    .map_err(|e| format!("Failed to fetch watermark: {}", e))?;
+                let image = ::image::load_from_memory(&bytes)
+                    .map_err(|e| format!("Failed to decode watermark: {}", e))?
+                    .to_rgba8();
+                let opacity = match std::env::var("WAVS_ENV_WATERMARK_OPACITY") {
+                    Ok(opacity) => opacity
+                        .parse()
+                        .map_err(|e| format!("Invalid WAVS_ENV_WATERMARK_OPACITY: {}", e))?,
+                    Err(_) => 50,
+                };
+                if opacity > 100 {
+                    return Err("WAVS_ENV_WATERMARK_OPACITY must be at most 100".to_string());
+                }
+                Some(Watermark { image, opacity })
+            }
+            _ => None,
+        };
+
+        Ok(PostProcessConfig { format, watermark })
+    }
+}
+
+/// JPEG quality from 1 to 100, 90 when unset
+fn jpeg_quality(quality: Option<&str>) -> Result<u8, String> {
+    let quality = match quality {
+        Some(quality) => {
+            quality.parse().map_err(|e| format!("Invalid WAVS_ENV_IMAGE_QUALITY: {}", e))?
+        }
+        None => 90,
+    };
+    if !(1..=100).contains(&quality) {
+        return Err("WAVS_ENV_IMAGE_QUALITY must be between 1 and 100".to_string());
+    }
+    Ok(quality)
+}
+
+/// Validate and normalize the image returned by Stable Diffusion, returning a PNG.
+///
+/// The image must decode, is resized to the requested dimensions when the
+/// server returned others, and gets the watermark. The result is re-encoded,
+/// which drops the text chunks SD servers use to embed the prompt and settings.
+pub fn clean(
+    bytes: &[u8],
+    width: u32,
+    height: u32,
+    config: &PostProcessConfig,
+) -> Result<Vec<u8>, String> {
+    let media_type = media::sniff(bytes)?;
+    let mut image = ::image::load_from_memory_with_format(bytes, media_type.image_format())
+        .map_err(|e| format!("Generated image does not decode: {}", e))?;
+
+    if (image.width(), image.height()) != (width, height) {
+        eprintln!(
+            "Generated image is {}x{} instead of {}x{}, resizing",
+            image.width(),
+            image.height(),
+            width,
+            height
+        );
+        image = image.resize_exact(width, height, FilterType::Lanczos3);
+    }
+
+    if let Some(watermark) = &config.watermark {
+        image = stamp(image, watermark);
+    }
+
+    encode(&image, OutputFormat::Png)
+}
+
+/// Re-encode a PNG derived from an image that was cleaned already, such as a Stable
+/// Diffusion upscale, dropping the text chunks the server embeds. The watermark is
+/// not stamped again, the upscale carries it scaled up.
+pub fn strip(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let media_type = media::sniff(bytes)?;
+    let image = ::image::load_from_memory_with_format(bytes, media_type.image_format())
+        .map_err(|e| format!("Upscaled image does not decode: {}", e))?;
+    encode(&image, OutputFormat::Png)
+}
+
+/// Overlay the watermark in the bottom right corner, at most a fifth of the image wide
+fn stamp(image: DynamicImage, watermark: &Watermark) -> DynamicImage {
+    let mut base = image.to_rgba8();
+    let max_width = (base.width() / 5).max(1);
+    let mut mark = if watermark.image.width() > max_width {
+        let height = watermark.image.height() * max_width / watermark.image.width();
+        imageops::resize(&watermark.image, max_width, height.max(1), FilterType::Lanczos3)
+    } else {
+        watermark.image.clone()
+    };
+    for pixel in mark.pixels_mut() {
+        pixel[3] = (u16::from(pixel[3]) * u16::from(watermark.opacity) / 100) as u8;
+    }
+
+    let margin = base.width() / 50;
+    let x = base.width().saturating_sub(mark.width() + margin);
+    let y = base.height().saturating_sub(mark.height() + margin);
+    imageops::overlay(&mut base, &mark, i64::from(x), i64::from(y));
+    DynamicImage::ImageRgba8(base)
+}
+
+/// Re-encode a still rendition in the output format, animations and PNGs that
+/// stay PNGs are left as they are
+pub fn reencode(rendition: Rendition, format: OutputFormat) -> Result<Rendition, String> {
+    let media_type = media::sniff(&rendition.bytes)?;
+    if media_type == MediaType::Gif || (media_type == MediaType::Png && format == OutputFormat::Png)
+    {
+        return Ok(rendition);
+    }
+    let image = ::image::load_from_memory_with_format(&rendition.bytes, media_type.image_format())
+        .map_err(|e| format!("Failed to decode {} rendition: {}", rendition.name, e))?;
+    Ok(Rendition { bytes: encode(&image, format)?, ..rendition })
+}
+
+fn encode(image: &DynamicImage, format: OutputFormat) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let result = match format {
+        OutputFormat::Png => image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png),
+        OutputFormat::WebP => {
+            image.to_rgba8().write_with_encoder(WebPEncoder::new_lossless(&mut bytes))
+        }
+        // JPEG has no alpha channel
+        OutputFormat::Jpeg(quality) => {
+            image.to_rgb8().write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, quality))
+        }
+    };
+    result.map_err(|e| format!("Failed to encode image: {}", e))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::image::{GenericImageView, Rgba};
+
+    const PLAIN: PostProcessConfig =
+        PostProcessConfig { format: OutputFormat::Png, watermark: None };
+
+    fn png(width: u32, height: u32, color: [u8; 4]) -> Vec<u8> {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba(color)));
+        encode(&image, OutputFormat::Png).unwrap()
+    }
+
+    fn crc32(bytes: &[u8]) -> u32 {
+        let mut crc = !0u32;
+        for &byte in bytes {
+            crc ^= u32::from(byte);
+            for _ in 0..8 {
+                crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            }
+        }
+        !crc
+    }
+
+    /// Insert a tEXt chunk after IHDR, as SD servers do with the generation settings
+    fn with_text(png: &[u8], text: &[u8]) -> Vec<u8> {
+        // Signature, then the IHDR chunk of 13 data bytes
+        let (head, tail) = png.split_at(8 + 8 + 13 + 4);
+        let mut chunk = b"tEXt".to_vec();
+        chunk.extend_from_slice(text);
+        let mut bytes = head.to_vec();
+        bytes.extend_from_slice(&(text.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&chunk);
+        bytes.extend_from_slice(&crc32(&chunk).to_be_bytes());
+        bytes.extend_from_slice(tail);
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> DynamicImage {
+        ::image::load_from_memory(bytes).unwrap()
+    }
+
+    #[test]
+    fn resizes_to_the_requested_size() {
+        let cleaned = clean(&png(64, 32, [0, 0, 255, 255]), 16, 16, &PLAIN).unwrap();
+        assert_eq!(media::sniff(&cleaned), Ok(MediaType::Png));
+        assert_eq!(decode(&cleaned).dimensions(), (16, 16));
+
+        assert!(clean(b"not an image", 16, 16, &PLAIN).is_err());
+    }
+
+    #[test]
+    fn drops_embedded_metadata() {
+        let original = with_text(&png(16, 16, [0, 0, 255, 255]), b"parameters\0a lighthouse");
+        assert_eq!(decode(&original).dimensions(), (16, 16));
+
+        let cleaned = clean(&original, 16, 16, &PLAIN).unwrap();
+        assert!(!cleaned.windows(4).any(|window| window == b"tEXt"));
+        assert!(!cleaned.windows(10).any(|window| window == b"lighthouse"));
+    }
+
+    #[test]
+    fn strips_upscaled_images() {
+        let upscaled = with_text(&png(32, 32, [0, 0, 255, 255]), b"parameters\0a lighthouse");
+        let stripped = strip(&upscaled).unwrap();
+        assert!(!stripped.windows(4).any(|window| window == b"tEXt"));
+        assert!(!stripped.windows(10).any(|window| window == b"lighthouse"));
+        assert_eq!(decode(&stripped).to_rgba8(), decode(&upscaled).to_rgba8());
+
+        // Already a PNG, so re-encoding in the output format leaves it as it is
+        let full = Rendition { name: "full", bytes: stripped.clone(), width: 32, height: 32 };
+        assert_eq!(reencode(full, OutputFormat::Png).unwrap().bytes, stripped);
+        assert!(strip(b"not an image").is_err());
+    }
+
+    #[test]
+    fn reads_the_jpeg_quality() {
+        assert_eq!(jpeg_quality(None), Ok(90));
+        assert_eq!(jpeg_quality(Some("75")), Ok(75));
+        assert!(jpeg_quality(Some("0")).is_err());
+        assert!(jpeg_quality(Some("101")).is_err());
+        assert!(jpeg_quality(Some("high")).is_err());
+    }
+
+    #[test]
+    fn stamps_the_watermark_in_the_corner() {
+        let mark = RgbaImage::from_pixel(8, 8, Rgba([255, 0, 0, 255]));
+        let config = |opacity| PostProcessConfig {
+            format: OutputFormat::Png,
+            watermark: Some(Watermark { image: mark.clone(), opacity }),
+        };
+        let original = png(64, 64, [0, 0, 255, 255]);
+
+        // A 1 pixel margin on a 64 pixel image puts the mark at 55 to 62
+        let stamped = decode(&clean(&original, 64, 64, &config(100)).unwrap());
+        assert_eq!(stamped.get_pixel(58, 58), Rgba([255, 0, 0, 255]));
+        assert_eq!(stamped.get_pixel(8, 8), Rgba([0, 0, 255, 255]));
+        assert_eq!(stamped.get_pixel(63, 63), Rgba([0, 0, 255, 255]));
+
+        let invisible = clean(&original, 64, 64, &config(0)).unwrap();
+        assert_eq!(invisible, clean(&original, 64, 64, &PLAIN).unwrap());
+    }
+
+    #[test]
+    fn reencodes_in_each_format() {
+        let rendition = Rendition {
+            name: "thumbnail",
+            bytes: png(16, 8, [0, 0, 255, 255]),
+            width: 16,
+            height: 8,
+        };
+
+        let webp = reencode(rendition.clone(), OutputFormat::WebP).unwrap();
+        assert_eq!(media::sniff(&webp.bytes), Ok(MediaType::WebP));
+        assert_eq!(decode(&webp.bytes).dimensions(), (16, 8));
+
+        let jpeg = reencode(rendition.clone(), OutputFormat::Jpeg(80)).unwrap();
+        assert_eq!(media::sniff(&jpeg.bytes), Ok(MediaType::Jpeg));
+        assert_eq!(decode(&jpeg.bytes).dimensions(), (16, 8));
+
+        let png = reencode(rendition.clone(), OutputFormat::Png).unwrap();
+        assert_eq!(png.bytes, rendition.bytes);
+    }
+
+    #[test]
+    fn leaves_animations_as_they_are() {
+        let gif = b"GIF89a\x01\x00\x01\x00\x00\x00\x00;".to_vec();
+        let preview = Rendition { name: "preview", bytes: gif.clone(), width: 1, height: 1 };
+        assert_eq!(reencode(preview, OutputFormat::Jpeg(80)).unwrap().bytes, gif);
+    }
+}
@@ -0,0 +1,259 @@
+use crate::image::{Lora, SdParams};
+use crate::ipfs;
+use crate::nft::Attribute;
+use serde::{Deserialize, Serialize};
+
+/// Persona of the built-in presets
+const DEFAULT_PERSONA: &str = "avant garde artist and philosopher Gilles Deleuze";
+
+/// Built-in styles, as (name, Stable Diffusion keywords), numbered from 1. The first adds no
+/// keywords.
+const BUILTIN_STYLES: [(&str, &str); 6] = [
+    ("Default", ""),
+    ("Oil Painting", "oil painting, thick impasto brush strokes, canvas texture"),
+    ("Watercolor", "watercolor painting, soft washes, paper texture"),
+    ("Pixel Art", "pixel art, 16-bit, crisp pixels"),
+    ("Photographic", "photograph, 35mm film, natural lighting"),
+    ("Line Art", "ink line art, monochrome linework, high contrast"),
+];
+
+/// A named style a collection can generate in
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StylePreset {
+    pub id: u16,
+    pub name: String,
+    /// Who the LLM writes as, e.g. "avant garde artist and philosopher Gilles Deleuze"
+    pub persona: String,
+    /// Keywords appended to the Stable Diffusion prompt
+    #[serde(default)]
+    pub keywords: String,
+    pub sd_model: String,
+    pub sampler: String,
+    #[serde(default)]
+    pub loras: Vec<Lora>,
+    pub negative_prompt: String,
+    /// Palette used when the trigger does not choose one
+    #[serde(default)]
+    pub palette: Option<String>,
+    /// Attributes added to every token in this style
+    #[serde(default)]
+    pub attributes: Vec<Attribute>,
+}
+
+impl StylePreset {
+    /// Apply the model, sampler, LoRAs and negative prompt to the generation parameters
+    pub fn apply_to(&self, params: &mut SdParams) {
+        params.model = self.sd_model.clone();
+        params.sampler_name = self.sampler.clone();
+        params.loras = self.loras.clone();
+        params.negative_prompt = self.negative_prompt.clone();
+    }
+
+    /// The preset trait followed by the preset's own attributes
+    pub fn attributes(&self) -> Vec<Attribute> {
+        let mut attributes = vec![Attribute::new("Style", self.name.clone())];
+        attributes.extend(self.attributes.iter().cloned());
+        attributes
+    }
+}
+
+/// Style presets available to a collection.
+///
+/// The registry is read from `WAVS_ENV_STYLE_PRESETS`, either inline JSON or a
+/// `ipfs://`, `https://` or `data:` URI pointing to it, and falls back to the
+/// built-in presets. Triggers choose a preset with `stylePresetId`, 0 selects
+/// the collection default, so preset IDs start at 1.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PresetRegistry {
+    #[serde(default = "first_preset")]
+    pub default_preset: u16,
+    pub presets: Vec<StylePreset>,
+}
+
+impl Default for PresetRegistry {
+    fn default() -> Self {
+        let defaults = SdParams::default();
+        let presets = BUILTIN_STYLES
+            .iter()
+            .enumerate()
+            .map(|(id, (name, keywords))| StylePreset {
+                id: id as u16 + 1,
+                name: name.to_string(),
+                persona: DEFAULT_PERSONA.to_string(),
+                keywords: keywords.to_string(),
+                sd_model: defaults.model.clone(),
+                sampler: defaults.sampler_name.clone(),
+                loras: Vec::new(),
+                negative_prompt: defaults.negative_prompt.clone(),
+                palette: None,
+                attributes: Vec::new(),
+            })
+            .collect();
+        PresetRegistry { default_preset: first_preset(), presets }
+    }
+}
+
+fn first_preset() -> u16 {
+    1
+}
+
+impl PresetRegistry {
+    /// Load the registry configured for this service
+    pub fn load() -> Result<Self, String> {
+        let mut registry = match std::env::var("WAVS_ENV_STYLE_PRESETS") {
+            Ok(source) if !source.trim().is_empty() => Self::from_source(&source)?,
+            _ => Self::default(),
+        };
+
+        if let Ok(id) = std::env::var("WAVS_ENV_DEFAULT_STYLE_PRESET") {
+            registry.default_preset =
+                id.parse().map_err(|e| format!("Invalid WAVS_ENV_DEFAULT_STYLE_PRESET: {}", e))?;
+        }
+
+        registry.validate()?;
+        Ok(registry)
+    }
+
+    fn from_source(source: &str) -> Result<Self, String> {
+        let json = ipfs::fetch_json_config(source)
+            .map_err(|e| format!("Failed to fetch style presets: {}", e))?;
+        serde_json::from_slice(&json).map_err(|e| format!("Failed to parse style presets: {}", e))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (i, preset) in self.presets.iter().enumerate() {
+            if preset.id == 0 {
+                return Err(format!(
+                    "Style preset {} cannot have id 0, which selects the default preset",
+                    preset.name
+                ));
+            }
+            if preset.name.trim().is_empty() || preset.persona.trim().is_empty() {
+                return Err(format!("Style preset {} must have a name and a persona", preset.id));
+            }
+            if self.presets[..i].iter().any(|other| other.id == preset.id) {
+                return Err(format!("Duplicate style preset id: {}", preset.id));
+            }
+            for attribute in &preset.attributes {
+                attribute.validate()?;
+            }
+        }
+
+        self.get(self.default_preset).map(|_| ())
+    }
+
+    pub fn get(&self, id: u16) -> Result<&StylePreset, String> {
+        self.presets
+            .iter()
+            .find(|preset| preset.id == id)
+            .ok_or_else(|| format!("Unknown style preset: {}", id))
+    }
+
+    /// Preset chosen by a trigger, 0 falls back to the collection default
+    pub fn select(&self, style_preset_id: u16) -> Result<&StylePreset, String> {
+        match style_preset_id {
+            0 => self.get(self.default_preset),
+            id => self.get(id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOIR: &str = r#"{
+        "default_preset": 2,
+        "presets": [
+            {"id": 1, "name": "Plain", "persona": "a painter", "sd_model": "v1-5-pruned-emaonly",
+             "sampler": "Euler", "negative_prompt": "blurry"},
+            {"id": 2, "name": "Noir", "persona": "a novelist", "keywords": "film noir",
+             "sd_model": "v1-5-pruned-emaonly", "sampler": "DPM++ 2M Karras",
+             "loras": [{"name": "noir_style", "weight": 0.8}], "negative_prompt": "blurry",
+             "palette": "monochrome", "attributes": [{"trait_type": "Series", "value": "Night City"}]}
+        ]
+    }"#;
+
+    fn parse(json: &str) -> Result<PresetRegistry, String> {
+        let registry = PresetRegistry::from_source(json)?;
+        registry.validate()?;
+        Ok(registry)
+    }
+
+    #[test]
+    fn selects_presets() {
+        let registry = PresetRegistry::default();
+        assert_eq!(registry.select(0).unwrap().name, "Default");
+        assert_eq!(registry.select(1).unwrap().name, "Default");
+        assert_eq!(registry.select(2).unwrap().name, "Oil Painting");
+        assert_eq!(registry.select(7).unwrap_err(), "Unknown style preset: 7");
+
+        // Every preset stays selectable when another one is the default
+        let registry = parse(NOIR).unwrap();
+        assert_eq!(registry.select(0).unwrap().name, "Noir");
+        assert_eq!(registry.select(1).unwrap().name, "Plain");
+        assert_eq!(registry.select(2).unwrap().name, "Noir");
+    }
+
+    #[test]
+    fn parses_inline_json_and_uris() {
+        let inline = parse(NOIR).unwrap();
+        let noir = inline.get(2).unwrap();
+        assert_eq!(noir.keywords, "film noir");
+        assert_eq!(noir.loras, [Lora { name: "noir_style".to_string(), weight: 0.8 }]);
+        assert_eq!(noir.palette.as_deref(), Some("monochrome"));
+        assert_eq!(noir.attributes().len(), 2);
+        assert_eq!(inline.get(1).unwrap().keywords, "");
+
+        let data_uri = format!(
+            "data:application/json;base64,{}",
+            base64::Engine::encode(&base64::engine::general_purpose::STANDARD, NOIR)
+        );
+        assert_eq!(parse(&data_uri).unwrap().default_preset, 2);
+
+        let without_default = r#"{"presets": [{"id": 1, "name": "Plain", "persona": "a painter",
+            "sd_model": "m", "sampler": "s", "negative_prompt": ""}]}"#;
+        assert_eq!(parse(without_default).unwrap().default_preset, 1);
+    }
+
+    #[test]
+    fn rejects_invalid_registries() {
+        let preset = |id: u16, name: &str| {
+            format!(
+                r#"{{"id": {}, "name": "{}", "persona": "a painter", "sd_model": "m", "sampler": "s", "negative_prompt": ""}}"#,
+                id, name
+            )
+        };
+        let registry = |default: u16, presets: &[String]| {
+            format!(r#"{{"default_preset": {}, "presets": [{}]}}"#, default, presets.join(","))
+        };
+
+        assert!(parse(&registry(0, &[preset(0, "Zero")]))
+            .unwrap_err()
+            .contains("cannot have id 0"));
+        assert!(parse(&registry(1, &[preset(1, "A"), preset(1, "B")]))
+            .unwrap_err()
+            .contains("Duplicate"));
+        assert!(parse(&registry(1, &[preset(1, " ")])).unwrap_err().contains("must have a name"));
+        assert_eq!(parse(&registry(3, &[preset(1, "A")])).unwrap_err(), "Unknown style preset: 3");
+        assert!(parse("{not json").unwrap_err().starts_with("Failed to"));
+    }
+
+    #[test]
+    fn loads_the_registry_from_the_environment() {
+        // The only test reading these variables
+        std::env::set_var("WAVS_ENV_STYLE_PRESETS", NOIR);
+        std::env::set_var("WAVS_ENV_DEFAULT_STYLE_PRESET", "1");
+        let registry = PresetRegistry::load();
+        std::env::remove_var("WAVS_ENV_STYLE_PRESETS");
+        std::env::set_var("WAVS_ENV_DEFAULT_STYLE_PRESET", "9");
+        let unknown_default = PresetRegistry::load();
+        std::env::remove_var("WAVS_ENV_DEFAULT_STYLE_PRESET");
+
+        let registry = registry.unwrap();
+        assert_eq!(registry.presets.len(), 2);
+        assert_eq!(registry.select(0).unwrap().name, "Plain");
+        assert_eq!(unknown_default.unwrap_err(), "Unknown style preset: 9");
+        assert_eq!(PresetRegistry::load().unwrap().presets.len(), BUILTIN_STYLES.len());
+    }
+}
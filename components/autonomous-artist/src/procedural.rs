@@ -0,0 +1,489 @@
+use crate::nft::{Attribute, AttributeValue};
+use ::image::{ImageFormat, Rgba, RgbaImage};
+use alloy_primitives::{keccak256, B256};
+use std::io::Cursor;
+
+/// What the procedural backend draws from: the prompt hash and the token's traits.
+///
+/// Every value is derived from these and the seed with integer hashing and basic
+/// float arithmetic, so every operator renders the same pixels without a network.
+#[derive(Debug, Clone)]
+pub struct ProceduralArt {
+    /// keccak256 of the prompt and the attributes of the token
+    hash: B256,
+    palette: Option<String>,
+    background: Option<String>,
+    frame: Option<String>,
+    mood: Option<String>,
+}
+
+/// Colors as red, green and blue from 0 to 1
+type Color = [f32; 3];
+
+#[derive(Debug, Clone)]
+enum Shape {
+    /// Center and radius relative to the image, the radius to its shorter side
+    Circle { x: f32, y: f32, radius: f32, color: Color, alpha: f32 },
+    /// Stripe through a point along the unit normal `(nx, ny)`, half as wide as `width`
+    Band { x: f32, y: f32, nx: f32, ny: f32, width: f32, color: Color, alpha: f32 },
+}
+
+enum Background {
+    Solid(Color),
+    /// Vertical gradient from top to bottom
+    Gradient(Color, Color),
+}
+
+/// A white dot drawn over the background, position and radius relative as for circles
+struct Star {
+    x: f32,
+    y: f32,
+    radius: f32,
+    alpha: f32,
+}
+
+/// A drawn composition, independent of the size it is rendered at
+struct Composition {
+    background: Background,
+    stars: Vec<Star>,
+    shapes: Vec<Shape>,
+    /// Border color and width relative to the shorter side
+    frame: Option<(Color, f32)>,
+}
+
+impl ProceduralArt {
+    pub fn new(prompt: &str, attributes: &[Attribute]) -> Self {
+        let mut material = prompt.as_bytes().to_vec();
+        for attribute in attributes {
+            material.extend_from_slice(attribute.trait_type.as_bytes());
+            material.push(0);
+            match &attribute.value {
+                AttributeValue::Text(value) => material.extend_from_slice(value.as_bytes()),
+                AttributeValue::Number(value) => material.extend_from_slice(&value.to_be_bytes()),
+            }
+            material.push(0);
+        }
+
+        let text = |trait_type: &str| {
+            attributes.iter().find(|a| a.trait_type == trait_type).and_then(|a| match &a.value {
+                AttributeValue::Text(value) => Some(value.to_lowercase()),
+                AttributeValue::Number(_) => None,
+            })
+        };
+        ProceduralArt {
+            hash: keccak256(material),
+            palette: text("Palette"),
+            background: text("Background"),
+            frame: text("Frame"),
+            mood: text("Mood"),
+        }
+    }
+
+    /// Render the composition drawn with `seed` as a PNG of the given size
+    pub fn render_png(&self, seed: i64, width: u32, height: u32) -> Result<Vec<u8>, String> {
+        if width == 0 || height == 0 {
+            return Err("Procedural image needs a non-zero size".to_string());
+        }
+        let image = self.compose(seed).rasterize(width, height);
+
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .map_err(|e| format!("Failed to encode procedural image: {}", e))?;
+        Ok(bytes)
+    }
+
+    /// Render the composition drawn with `seed` as an SVG document of the given size
+    pub fn render_svg(&self, seed: i64, width: u32, height: u32) -> String {
+        self.compose(seed).to_svg(width, height)
+    }
+
+    fn compose(&self, seed: i64) -> Composition {
+        let mut material = self.hash.to_vec();
+        material.extend_from_slice(&seed.to_be_bytes());
+        let mut rng = Rng(u64::from_be_bytes(keccak256(material)[..8].try_into().unwrap()));
+
+        // Traits of custom tables without a known value get a palette drawn from the hash
+        const PALETTES: [&str; 6] = ["vivid", "pastel", "earth", "monochrome", "neon", "sepia"];
+        let palette = match self.palette.as_deref() {
+            Some(palette) if PALETTES.contains(&palette) => palette.to_string(),
+            _ => PALETTES[rng.below(PALETTES.len())].to_string(),
+        };
+        let palette = Palette { name: palette, hue: rng.range(0.0, 360.0) };
+
+        let background = match self.background.as_deref() {
+            Some("plain") => Background::Solid(palette.color(&mut rng)),
+            Some("cosmic") => Background::Gradient(hsl(230.0, 0.5, 0.12), hsl(270.0, 0.4, 0.04)),
+            Some("gilded") => Background::Gradient(hsl(45.0, 0.75, 0.62), hsl(38.0, 0.7, 0.4)),
+            Some("void") => Background::Solid([0.0; 3]),
+            _ => Background::Gradient(palette.color(&mut rng), palette.color(&mut rng)),
+        };
+
+        let mut stars = Vec::new();
+        if self.background.as_deref() == Some("cosmic") {
+            for _ in 0..120 {
+                let (radius, alpha) = (rng.range(0.001, 0.004), rng.range(0.5, 1.0));
+                stars.push(Star { x: rng.unit(), y: rng.unit(), radius, alpha });
+            }
+        }
+
+        let mut shapes = Vec::new();
+
+        // The mood sets how many shapes there are, how large and how opaque
+        let (count, min_radius, max_radius, alpha, band_share) = match self.mood.as_deref() {
+            Some("serene") => (5, 0.15, 0.35, 0.55, 0.2),
+            Some("melancholic") => (7, 0.08, 0.25, 0.5, 0.6),
+            Some("joyful") => (18, 0.03, 0.12, 0.85, 0.1),
+            Some("ominous") => (9, 0.1, 0.3, 0.8, 0.5),
+            Some("ecstatic") => (28, 0.02, 0.15, 0.9, 0.35),
+            _ => (10, 0.05, 0.25, 0.7, 0.3),
+        };
+        for _ in 0..count {
+            let mut color = palette.color(&mut rng);
+            if self.mood.as_deref() == Some("ominous") {
+                color = color.map(|c| c * 0.45);
+            }
+            let alpha = alpha * rng.range(0.7, 1.0);
+            if rng.unit() < band_share {
+                // A random direction, normalized without trigonometry
+                let (dx, dy) = (rng.range(-1.0, 1.0), rng.range(-1.0, 1.0));
+                let length = (dx * dx + dy * dy).sqrt().max(0.01);
+                shapes.push(Shape::Band {
+                    x: rng.unit(),
+                    y: rng.unit(),
+                    nx: dx / length,
+                    ny: dy / length,
+                    width: rng.range(min_radius * 0.5, max_radius),
+                    color,
+                    alpha,
+                });
+            } else {
+                let radius = rng.range(min_radius, max_radius);
+                shapes.push(Shape::circle(&mut rng, radius, color, alpha));
+            }
+        }
+
+        let frame = match self.frame.as_deref() {
+            Some("wooden") => Some((hsl(25.0, 0.45, 0.3), 0.05)),
+            Some("ornate gold") => Some((hsl(45.0, 0.8, 0.5), 0.06)),
+            Some("neon") => Some((hsl(palette.hue, 1.0, 0.55), 0.025)),
+            _ => None,
+        };
+
+        Composition { background, stars, shapes, frame }
+    }
+}
+
+impl Shape {
+    fn circle(rng: &mut Rng, radius: f32, color: Color, alpha: f32) -> Self {
+        Shape::Circle { x: rng.unit(), y: rng.unit(), radius, color, alpha }
+    }
+}
+
+impl Composition {
+    fn rasterize(&self, width: u32, height: u32) -> RgbaImage {
+        let (w, h) = (width as f32, height as f32);
+        let scale = w.min(h);
+        let mut pixels: Vec<Color> = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            let color = match &self.background {
+                Background::Solid(color) => *color,
+                Background::Gradient(top, bottom) => mix(*top, *bottom, (y as f32 + 0.5) / h),
+            };
+            pixels.resize(pixels.len() + width as usize, color);
+        }
+
+        // Stars lie under every other shape
+        let stars = self.stars.iter().map(|star| Shape::Circle {
+            x: star.x,
+            y: star.y,
+            radius: star.radius,
+            color: [1.0; 3],
+            alpha: star.alpha,
+        });
+        for shape in stars.chain(self.shapes.iter().cloned()) {
+            match shape {
+                Shape::Circle { x, y, radius, color, alpha } => {
+                    let (cx, cy, r) = (x * w, y * h, radius * scale);
+                    let x0 = (cx - r - 1.0).max(0.0) as u32;
+                    let x1 = ((cx + r + 1.0).max(0.0) as u32).min(width);
+                    let y0 = (cy - r - 1.0).max(0.0) as u32;
+                    let y1 = ((cy + r + 1.0).max(0.0) as u32).min(height);
+                    for py in y0..y1 {
+                        for px in x0..x1 {
+                            let (dx, dy) = (px as f32 + 0.5 - cx, py as f32 + 0.5 - cy);
+                            // Anti-aliased edge over one pixel
+                            let coverage = (r - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0);
+                            blend(&mut pixels[(py * width + px) as usize], color, alpha * coverage);
+                        }
+                    }
+                }
+                Shape::Band { x, y, nx, ny, width: band, color, alpha } => {
+                    let (cx, cy, half) = (x * w, y * h, band * scale / 2.0);
+                    for py in 0..height {
+                        for px in 0..width {
+                            let d =
+                                ((px as f32 + 0.5 - cx) * nx + (py as f32 + 0.5 - cy) * ny).abs();
+                            let coverage = (half - d + 0.5).clamp(0.0, 1.0);
+                            if coverage > 0.0 {
+                                blend(
+                                    &mut pixels[(py * width + px) as usize],
+                                    color,
+                                    alpha * coverage,
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some((color, border)) = self.frame {
+            let border = (border * scale).round().max(1.0) as u32;
+            for py in 0..height {
+                for px in 0..width {
+                    let edge = px.min(py).min(width - 1 - px).min(height - 1 - py);
+                    if edge < border {
+                        pixels[(py * width + px) as usize] = color;
+                    }
+                }
+            }
+        }
+
+        RgbaImage::from_fn(width, height, |x, y| {
+            let [r, g, b] = pixels[(y * width + x) as usize].map(to_byte);
+            Rgba([r, g, b, 255])
+        })
+    }
+}
+
+impl Composition {
+    /// The same composition as vector shapes, kept compact to be stored on-chain
+    fn to_svg(&self, width: u32, height: u32) -> String {
+        let (w, h) = (width as f32, height as f32);
+        let scale = w.min(h);
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\">",
+            width, height
+        );
+        match &self.background {
+            Background::Solid(color) => svg.push_str(&format!(
+                "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                width,
+                height,
+                hex(*color)
+            )),
+            Background::Gradient(top, bottom) => svg.push_str(&format!(
+                "<defs><linearGradient id=\"b\" x2=\"0\" y2=\"1\"><stop stop-color=\"{}\"/><stop offset=\"1\" stop-color=\"{}\"/></linearGradient></defs><rect width=\"{}\" height=\"{}\" fill=\"url(#b)\"/>",
+                hex(*top),
+                hex(*bottom),
+                width,
+                height
+            )),
+        }
+
+        // Stars are zero-length strokes with round caps in a single path, at their mean size
+        if !self.stars.is_empty() {
+            let count = self.stars.len() as f32;
+            let radius = self.stars.iter().map(|star| star.radius).sum::<f32>() / count;
+            let alpha = self.stars.iter().map(|star| star.alpha).sum::<f32>() / count;
+            let dots: String = self
+                .stars
+                .iter()
+                .map(|star| format!("M{} {}h0", num(star.x * w), num(star.y * h)))
+                .collect();
+            svg.push_str(&format!(
+                "<path d=\"{}\" stroke=\"#fff\" stroke-width=\"{}\" stroke-opacity=\"{}\" stroke-linecap=\"round\"/>",
+                dots,
+                num(2.0 * radius * scale),
+                num(alpha)
+            ));
+        }
+
+        for shape in &self.shapes {
+            match *shape {
+                Shape::Circle { x, y, radius, color, alpha } => svg.push_str(&format!(
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" fill-opacity=\"{}\"/>",
+                    num(x * w),
+                    num(y * h),
+                    num(radius * scale),
+                    hex(color),
+                    num(alpha)
+                )),
+                Shape::Band { x, y, nx, ny, width: band, color, alpha } => {
+                    // A quadrilateral long enough to cross the whole image
+                    let (cx, cy, half, length) = (x * w, y * h, band * scale / 2.0, w + h);
+                    let (tx, ty) = (-ny, nx);
+                    let points: Vec<String> = [(1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0), (1.0, -1.0)]
+                        .iter()
+                        .map(|(along, across)| {
+                            let px = cx + along * length * tx + across * half * nx;
+                            let py = cy + along * length * ty + across * half * ny;
+                            format!("{} {}", num(px), num(py))
+                        })
+                        .collect();
+                    svg.push_str(&format!(
+                        "<polygon points=\"{}\" fill=\"{}\" fill-opacity=\"{}\"/>",
+                        points.join(" "),
+                        hex(color),
+                        num(alpha)
+                    ));
+                }
+            }
+        }
+
+        if let Some((color, border)) = self.frame {
+            let border = border * scale;
+            svg.push_str(&format!(
+                "<rect x=\"{0}\" y=\"{0}\" width=\"{1}\" height=\"{2}\" fill=\"none\" stroke=\"{3}\" stroke-width=\"{4}\"/>",
+                num(border / 2.0),
+                num(w - border),
+                num(h - border),
+                hex(color),
+                num(border)
+            ));
+        }
+
+        svg.push_str("</svg>");
+        svg
+    }
+}
+
+/// Colors of a palette trait around a hue drawn per composition
+struct Palette {
+    name: String,
+    hue: f32,
+}
+
+impl Palette {
+    fn color(&self, rng: &mut Rng) -> Color {
+        let hue = self.hue + rng.range(-40.0, 40.0);
+        match self.name.as_str() {
+            "pastel" => hsl(hue, 0.6, rng.range(0.75, 0.88)),
+            "earth" => hsl(rng.range(20.0, 110.0), rng.range(0.25, 0.45), rng.range(0.25, 0.55)),
+            "monochrome" => hsl(0.0, 0.0, rng.unit()),
+            "neon" => hsl(hue + 120.0 * rng.below(3) as f32, 1.0, 0.55),
+            "sepia" => hsl(rng.range(28.0, 40.0), 0.4, rng.range(0.25, 0.8)),
+            _ => hsl(hue + 180.0 * rng.below(2) as f32, rng.range(0.7, 0.95), rng.range(0.45, 0.6)),
+        }
+    }
+}
+
+fn hsl(hue: f32, saturation: f32, lightness: f32) -> Color {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    [r + m, g + m, b + m]
+}
+
+fn mix(a: Color, b: Color, t: f32) -> Color {
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t]
+}
+
+fn blend(pixel: &mut Color, color: Color, alpha: f32) {
+    *pixel = mix(*pixel, color, alpha);
+}
+
+fn to_byte(value: f32) -> u8 {
+    (value * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+fn hex(color: Color) -> String {
+    let [r, g, b] = color.map(to_byte);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// A coordinate with at most two decimals and no trailing zeros
+fn num(value: f32) -> String {
+    let text = format!("{:.2}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" => "0".to_string(),
+        text => text.to_string(),
+    }
+}
+
+/// SplitMix64, small and identical on every platform
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    fn unit(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.unit()
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn art(prompt: &str) -> ProceduralArt {
+        ProceduralArt::new(
+            prompt,
+            &[
+                Attribute::new("Palette", "neon"),
+                Attribute::new("Background", "Cosmic"),
+                Attribute::new("Frame", "Ornate Gold"),
+                Attribute::new("Mood", "Joyful"),
+            ],
+        )
+    }
+
+    #[test]
+    fn renders_deterministically() {
+        let png = art("a lighthouse").render_png(42, 64, 48).unwrap();
+        assert_eq!(png, art("a lighthouse").render_png(42, 64, 48).unwrap());
+        assert_ne!(png, art("a lighthouse").render_png(43, 64, 48).unwrap());
+        assert_ne!(png, art("a harbor").render_png(42, 64, 48).unwrap());
+
+        let image = ::image::load_from_memory_with_format(&png, ImageFormat::Png).unwrap();
+        assert_eq!((image.width(), image.height()), (64, 48));
+    }
+
+    #[test]
+    fn draws_the_frame_trait() {
+        let png = art("a lighthouse").render_png(42, 100, 100).unwrap();
+        let image = ::image::load_from_memory(&png).unwrap().to_rgb8();
+        let gold = hsl(45.0, 0.8, 0.5).map(to_byte);
+        assert_eq!(image.get_pixel(0, 0).0, gold);
+        assert_eq!(image.get_pixel(99, 50).0, gold);
+
+        let unframed = ProceduralArt::new("a lighthouse", &[Attribute::new("Frame", "None")]);
+        let png = unframed.render_png(42, 100, 100).unwrap();
+        let image = ::image::load_from_memory(&png).unwrap().to_rgb8();
+        assert_ne!(image.get_pixel(0, 0).0, gold);
+    }
+
+    #[test]
+    fn renders_svg_within_the_sanitizer() {
+        let svg = art("a lighthouse").render_svg(42, 512, 512);
+        assert_eq!(svg, art("a lighthouse").render_svg(42, 512, 512));
+        assert!(svg.contains("stroke=\"#e6b319\""));
+        assert_eq!(crate::svg::sanitize(&svg, 16 * 1024), Ok(svg));
+    }
+}
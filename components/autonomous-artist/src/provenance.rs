@@ -0,0 +1,166 @@
+use crate::image::{Lora, SdParams};
+use crate::transcript::Stage;
+use alloy_primitives::{keccak256, Address};
+use serde::Serialize;
+
+/// Where the trigger that produced a token came from
+#[derive(Debug, Clone)]
+pub struct TriggerOrigin {
+    pub chain_name: String,
+    pub contract: Address,
+    pub block_number: u64,
+}
+
+/// How a token was produced, embedded in the metadata so anyone can audit or
+/// regenerate the artwork
+#[derive(Serialize, Debug, Clone)]
+pub struct Provenance {
+    pub chain_id: String,
+    pub trigger_contract: String,
+    pub trigger_id: u64,
+    pub block_number: u64,
+    /// keccak256 of the original prompt
+    pub prompt_hash: String,
+    pub llm: LlmProvenance,
+    pub stable_diffusion: SdProvenance,
+    /// Backend that drew the image when it was not Stable Diffusion, such as `procedural`
+    /// or `llm_svg`, whose parameters then only record the seed and size it was drawn with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_backend: Option<String>,
+    pub component: String,
+    pub component_version: String,
+    /// Digests of the intermediate outputs, see `Transcript`
+    pub digests: Vec<Stage>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct LlmProvenance {
+    pub model: String,
+    pub context_window: u32,
+    pub max_tokens: u32,
+    pub seed: u32,
+    pub temperature: f64,
+    pub top_p: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SdProvenance {
+    pub model: String,
+    pub sampler: String,
+    pub seed: i64,
+    pub steps: u32,
+    pub cfg_scale: f64,
+    pub width: u32,
+    pub height: u32,
+    pub negative_prompt: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub loras: Vec<Lora>,
+}
+
+impl From<&SdParams> for SdProvenance {
+    fn from(params: &SdParams) -> Self {
+        SdProvenance {
+            model: params.model.clone(),
+            sampler: params.sampler_name.clone(),
+            seed: params.seed,
+            steps: params.steps,
+            cfg_scale: params.cfg_scale,
+            width: params.width,
+            height: params.height,
+            negative_prompt: params.negative_prompt.clone(),
+            loras: params.loras.clone(),
+        }
+    }
+}
+
+impl Provenance {
+    pub fn new(
+        chain_id: String,
+        origin: &TriggerOrigin,
+        trigger_id: u64,
+        prompt: &str,
+        llm: LlmProvenance,
+        sd_params: &SdParams,
+        digests: Vec<Stage>,
+    ) -> Self {
+        Provenance {
+            chain_id,
+            trigger_contract: origin.contract.to_string(),
+            trigger_id,
+            block_number: origin.block_number,
+            prompt_hash: keccak256(prompt.as_bytes()).to_string(),
+            llm,
+            stable_diffusion: sd_params.into(),
+            image_backend: None,
+            component: env!("CARGO_PKG_NAME").to_string(),
+            component_version: env!("CARGO_PKG_VERSION").to_string(),
+            digests,
+        }
+    }
+
+    /// Record the backend that drew the image, None for Stable Diffusion which is left implicit
+    pub fn with_image_backend(mut self, backend: Option<&str>) -> Self {
+        self.image_backend = backend.map(str::to_string);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canonical::to_canonical_json;
+
+    #[test]
+    fn serializes_the_provenance_block() {
+        let origin = TriggerOrigin {
+            chain_name: "local".to_string(),
+            contract: "0x5FbDB2315678afecb367f032d93F642f64180aa3".parse().unwrap(),
+            block_number: 42,
+        };
+        let llm = LlmProvenance {
+            model: "llama3.2".to_string(),
+            context_window: 1024,
+            max_tokens: 1024,
+            seed: 42,
+            temperature: 0.7,
+            top_p: 0.9,
+        };
+        let sd_params = SdParams {
+            loras: vec![Lora { name: "ink".to_string(), weight: 0.8 }],
+            ..SdParams::default()
+        };
+        let digests = vec![Stage { name: "prompt".to_string(), digest: "0x01".to_string() }];
+        let provenance = Provenance::new(
+            "31337".to_string(),
+            &origin,
+            3,
+            "a lighthouse",
+            llm,
+            &sd_params,
+            digests,
+        );
+
+        assert_eq!(
+            to_canonical_json(&provenance).unwrap(),
+            format!(
+                concat!(
+                    r#"{{"block_number":42,"chain_id":"31337","component":"{}","component_version":"{}","#,
+                    r#""digests":[{{"digest":"0x01","name":"prompt"}}],"#,
+                    r#""llm":{{"context_window":1024,"max_tokens":1024,"model":"llama3.2","seed":42,"temperature":0.7,"top_p":0.9}},"#,
+                    r#""prompt_hash":"0x5f3d110c8f8fece772c3aeb4dcca539a3f8b603f4ed4d7189e2d2faa440f909b","#,
+                    r#""stable_diffusion":{{"cfg_scale":7,"height":512,"loras":[{{"name":"ink","weight":0.8}}],"#,
+                    r#""model":"v1-5-pruned-emaonly","negative_prompt":"blurry, bad quality, distorted, nonsquare, not full, borders","#,
+                    r#""sampler":"DPM++ 2M Karras","seed":42,"steps":30,"width":512}},"#,
+                    r#""trigger_contract":"0x5FbDB2315678afecb367f032d93F642f64180aa3","trigger_id":3}}"#
+                ),
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+
+        let procedural = provenance.with_image_backend(Some("procedural"));
+        assert!(to_canonical_json(&procedural)
+            .unwrap()
+            .contains(r#""image_backend":"procedural""#));
+    }
+}
@@ -0,0 +1,236 @@
+use crate::image::{self, Backend, SdParams};
+use crate::media;
+use crate::postprocess::{self, PostProcessConfig};
+use crate::procedural::ProceduralArt;
+use ::image::codecs::gif::{GifEncoder, Repeat};
+use ::image::imageops::FilterType;
+use ::image::{Delay, DynamicImage, Frame, ImageFormat};
+use std::io::Cursor;
+
+/// Which renditions to produce besides the generated image, read from the environment
+#[derive(Debug, Clone)]
+pub struct RenditionConfig {
+    /// Longest side of the thumbnail in pixels, 0 disables it
+    pub thumbnail_size: u32,
+    /// Upscale factor of the full-size rendition, via the Stable Diffusion extras endpoint
+    pub upscale_factor: Option<f32>,
+    /// Number of seeds the animated preview cycles through, below 2 disables it
+    pub animation_frames: u32,
+}
+
+impl RenditionConfig {
+    pub fn from_env() -> Result<Self, String> {
+        Ok(RenditionConfig {
+            thumbnail_size: env_or("WAVS_ENV_THUMBNAIL_SIZE", 256)?,
+            upscale_factor: match std::env::var("WAVS_ENV_SD_UPSCALE_FACTOR") {
+                Ok(factor) => Some(
+                    factor
+                        .parse()
+                        .map_err(|e| format!("Invalid WAVS_ENV_SD_UPSCALE_FACTOR: {}", e))?,
+                ),
+                Err(_) => None,
+            },
+            animation_frames: env_or("WAVS_ENV_ANIMATION_FRAMES", 0)?,
+        })
+    }
+}
+
+fn env_or(name: &str, default: u32) -> Result<u32, String> {
+    match std::env::var(name) {
+        Ok(value) => value.parse().map_err(|e| format!("Invalid {}: {}", name, e)),
+        Err(_) => Ok(default),
+    }
+}
+
+/// One encoded version of a token's image, its format is read from the bytes on upload
+#[derive(Debug, Clone)]
+pub struct Rendition {
+    /// Role of the rendition: "original", "full", "thumbnail" or "preview"
+    pub name: &'static str,
+    pub bytes: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rendition {
+    /// Wrap an image produced elsewhere, reading its format and dimensions
+    pub fn new(name: &'static str, bytes: Vec<u8>) -> Result<Self, String> {
+        let media_type = media::verify(&bytes, media::max_image_bytes()?)?;
+        let image = ::image::load_from_memory_with_format(&bytes, media_type.image_format())
+            .map_err(|e| format!("Failed to decode {} rendition: {}", name, e))?;
+        Ok(Rendition { name, width: image.width(), height: image.height(), bytes })
+    }
+}
+
+fn decode_png(bytes: &[u8]) -> Result<DynamicImage, String> {
+    ::image::load_from_memory_with_format(bytes, ImageFormat::Png)
+        .map_err(|e| format!("Failed to decode PNG: {}", e))
+}
+
+/// Downscale a PNG so its longest side is `max_size`, keeping the aspect ratio
+pub fn thumbnail(png: &[u8], max_size: u32) -> Result<Rendition, String> {
+    let image = decode_png(png)?.resize(max_size, max_size, FilterType::Lanczos3);
+
+    let mut bytes = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode thumbnail: {}", e))?;
+
+    Ok(Rendition { name: "thumbnail", width: image.width(), height: image.height(), bytes })
+}
+
+/// Looping GIF cycling through the given PNG frames, scaled to `max_size`
+pub fn animated_preview(frames: &[Vec<u8>], max_size: u32) -> Result<Rendition, String> {
+    let frames = frames
+        .iter()
+        .map(|png| Ok(decode_png(png)?.resize(max_size, max_size, FilterType::Triangle).to_rgba8()))
+        .collect::<Result<Vec<_>, String>>()?;
+    let (width, height) = frames.first().ok_or("Animated preview needs frames")?.dimensions();
+
+    let mut bytes = Vec::new();
+    {
+        // Speed 10 is the encoder's default trade-off between palette quality and fuel
+        let mut encoder = GifEncoder::new_with_speed(&mut bytes, 10);
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(|e| format!("Failed to encode animated preview: {}", e))?;
+        encoder
+            .encode_frames(
+                frames.into_iter().map(|frame| {
+                    Frame::from_parts(frame, 0, 0, Delay::from_numer_denom_ms(500, 1))
+                }),
+            )
+            .map_err(|e| format!("Failed to encode animated preview: {}", e))?;
+    }
+
+    Ok(Rendition { name: "preview", bytes, width, height })
+}
+
+/// Produce the configured renditions of a generated PNG.
+///
+/// Returns the main rendition, the upscale when enabled and the original
+/// otherwise, followed by the remaining renditions. `original` has been
+/// post-processed already, the extra animation frames are post-processed here.
+/// Frames and upscales come from the `backend` that generated the original, procedural
+/// art is upscaled by rendering it again at the larger size.
+pub fn produce(
+    original: Vec<u8>,
+    sd_prompt: &str,
+    sd_params: &SdParams,
+    art: &ProceduralArt,
+    backend: Backend,
+    config: &RenditionConfig,
+    post: &PostProcessConfig,
+) -> Result<(Rendition, Vec<Rendition>), String> {
+    let mut extras = Vec::new();
+
+    if config.thumbnail_size > 0 {
+        extras.push(thumbnail(&original, config.thumbnail_size)?);
+    }
+
+    if config.animation_frames > 1 {
+        // Frame 0 is the original, the others use the following seeds
+        let mut frames = vec![original.clone()];
+        for offset in 1..i64::from(config.animation_frames) {
+            let params = SdParams { seed: sd_params.seed + offset, ..sd_params.clone() };
+            let (frame, _) = image::generate(sd_prompt, &params, art, &[backend])?;
+            frames.push(postprocess::clean(&frame, sd_params.width, sd_params.height, post)?);
+        }
+        let size = if config.thumbnail_size > 0 { config.thumbnail_size } else { 256 };
+        extras.push(animated_preview(&frames, size)?);
+    }
+
+    let original = Rendition::new("original", original)?;
+    match config.upscale_factor {
+        Some(factor) => {
+            let upscaled = match backend {
+                Backend::StableDiffusion => {
+                    postprocess::strip(&image::upscale_image(&original.bytes, factor)?)?
+                }
+                Backend::Procedural => {
+                    let width = (original.width as f32 * factor).round() as u32;
+                    let height = (original.height as f32 * factor).round() as u32;
+                    let png = art.render_png(sd_params.seed, width, height)?;
+                    postprocess::clean(&png, width, height, post)?
+                }
+            };
+            let full = Rendition::new("full", upscaled)?;
+            extras.insert(0, original);
+            Ok((full, extras))
+        }
+        None => Ok((original, extras)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::postprocess::OutputFormat;
+    use ::image::codecs::gif::GifDecoder;
+    use ::image::AnimationDecoder;
+
+    fn art() -> ProceduralArt {
+        ProceduralArt::new("a lighthouse", &[])
+    }
+
+    #[test]
+    fn thumbnails_keep_the_aspect_ratio() {
+        let png = art().render_png(1, 64, 32).unwrap();
+        let thumbnail = thumbnail(&png, 16).unwrap();
+        assert_eq!((thumbnail.width, thumbnail.height), (16, 8));
+
+        let decoded = decode_png(&thumbnail.bytes).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (16, 8));
+        assert!(super::thumbnail(b"not a png", 16).is_err());
+    }
+
+    #[test]
+    fn encodes_looping_previews() {
+        let frames: Vec<_> = (0..3).map(|seed| art().render_png(seed, 64, 64).unwrap()).collect();
+        let preview = animated_preview(&frames, 32).unwrap();
+        assert_eq!((preview.name, preview.width, preview.height), ("preview", 32, 32));
+
+        let decoder = GifDecoder::new(Cursor::new(&preview.bytes)).unwrap();
+        assert_eq!(decoder.into_frames().count(), 3);
+        assert_eq!(animated_preview(&[], 32).unwrap_err(), "Animated preview needs frames");
+    }
+
+    #[test]
+    fn reads_the_size_of_renditions() {
+        let rendition = Rendition::new("original", art().render_png(1, 48, 24).unwrap()).unwrap();
+        assert_eq!((rendition.width, rendition.height), (48, 24));
+        assert!(Rendition::new("original", b"not an image".to_vec()).is_err());
+    }
+
+    #[test]
+    fn produces_the_configured_renditions() {
+        let params = SdParams { width: 32, height: 32, ..SdParams::default() };
+        let original = art().render_png(params.seed, 32, 32).unwrap();
+        let config =
+            RenditionConfig { thumbnail_size: 16, upscale_factor: Some(2.0), animation_frames: 2 };
+        let post = PostProcessConfig { format: OutputFormat::Png, watermark: None };
+
+        let (main, extras) = produce(
+            original.clone(),
+            "a lighthouse",
+            &params,
+            &art(),
+            Backend::Procedural,
+            &config,
+            &post,
+        )
+        .unwrap();
+        assert_eq!((main.name, main.width, main.height), ("full", 64, 64));
+        let names: Vec<_> = extras.iter().map(|rendition| rendition.name).collect();
+        assert_eq!(names, ["original", "thumbnail", "preview"]);
+        assert_eq!(extras[0].bytes, original);
+
+        let config =
+            RenditionConfig { thumbnail_size: 0, upscale_factor: None, animation_frames: 0 };
+        let (main, extras) =
+            produce(original, "a lighthouse", &params, &art(), Backend::Procedural, &config, &post)
+                .unwrap();
+        assert_eq!(main.name, "original");
+        assert!(extras.is_empty());
+    }
+}
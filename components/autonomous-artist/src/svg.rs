@@ -0,0 +1,374 @@
+use crate::image::SdParams;
+use crate::procedural::ProceduralArt;
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+
+/// Largest SVG stored on-chain, unless configured
+const DEFAULT_MAX_SVG_BYTES: usize = 8 * 1024;
+
+/// Elements kept below the root, anything else is dropped with its children
+const ELEMENTS: [&str; 24] = [
+    "g",
+    "defs",
+    "rect",
+    "circle",
+    "ellipse",
+    "line",
+    "polyline",
+    "polygon",
+    "path",
+    "linearGradient",
+    "radialGradient",
+    "stop",
+    "clipPath",
+    "mask",
+    "filter",
+    "feGaussianBlur",
+    "feOffset",
+    "feBlend",
+    "feColorMatrix",
+    "feTurbulence",
+    "feDisplacementMap",
+    "feMerge",
+    "text",
+    "tspan",
+];
+
+/// Elements whose text content is kept
+const TEXT_ELEMENTS: [&str; 2] = ["text", "tspan"];
+
+/// Geometry and presentation attributes kept, event handlers, links and styles are dropped
+const ATTRIBUTES: [&str; 67] = [
+    "id",
+    "x",
+    "y",
+    "x1",
+    "y1",
+    "x2",
+    "y2",
+    "cx",
+    "cy",
+    "r",
+    "rx",
+    "ry",
+    "fx",
+    "fy",
+    "width",
+    "height",
+    "d",
+    "points",
+    "viewBox",
+    "preserveAspectRatio",
+    "transform",
+    "fill",
+    "fill-opacity",
+    "fill-rule",
+    "stroke",
+    "stroke-width",
+    "stroke-opacity",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+    "stroke-miterlimit",
+    "opacity",
+    "offset",
+    "stop-color",
+    "stop-opacity",
+    "gradientUnits",
+    "gradientTransform",
+    "spreadMethod",
+    "clip-path",
+    "clip-rule",
+    "clipPathUnits",
+    "mask",
+    "maskUnits",
+    "filter",
+    "filterUnits",
+    "stdDeviation",
+    "in",
+    "in2",
+    "result",
+    "mode",
+    "type",
+    "values",
+    "baseFrequency",
+    "numOctaves",
+    "seed",
+    "scale",
+    "xChannelSelector",
+    "yChannelSelector",
+    "dx",
+    "dy",
+    "font-family",
+    "font-size",
+    "font-weight",
+    "font-style",
+    "text-anchor",
+    "letter-spacing",
+];
+
+/// Where the token's art and metadata are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Images and metadata uploaded to IPFS, the token URI links to the metadata
+    Ipfs,
+    /// An SVG in `image_data` of metadata held in the token URI itself
+    OnChain,
+}
+
+impl OutputMode {
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var("WAVS_ENV_OUTPUT_MODE").as_deref() {
+            Err(_) | Ok("ipfs") => Ok(OutputMode::Ipfs),
+            Ok("onchain") => Ok(OutputMode::OnChain),
+            Ok(other) => Err(format!("Unsupported WAVS_ENV_OUTPUT_MODE: {}", other)),
+        }
+    }
+}
+
+/// Where on-chain SVGs come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SvgSource {
+    /// Drawn by the LLM from the Stable Diffusion prompt
+    Llm,
+    /// The procedural composition of the prompt hash and traits
+    Procedural,
+}
+
+impl SvgSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SvgSource::Llm => "llm_svg",
+            SvgSource::Procedural => "procedural_svg",
+        }
+    }
+}
+
+/// How on-chain SVGs are produced, read from the environment
+#[derive(Debug, Clone)]
+pub struct SvgConfig {
+    /// Sources tried in order until one produces an SVG within the budget
+    pub sources: Vec<SvgSource>,
+    /// Largest sanitized SVG accepted, in bytes
+    pub max_bytes: usize,
+}
+
+impl SvgConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let sources = std::env::var("WAVS_ENV_SVG_SOURCE")
+            .unwrap_or_else(|_| "llm,procedural".to_string())
+            .split(',')
+            .map(|source| match source.trim() {
+                "llm" => Ok(SvgSource::Llm),
+                "procedural" => Ok(SvgSource::Procedural),
+                other => Err(format!("Unsupported WAVS_ENV_SVG_SOURCE: {}", other)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let max_bytes = match std::env::var("WAVS_ENV_SVG_MAX_BYTES") {
+            Ok(max) => max.parse().map_err(|e| format!("Invalid WAVS_ENV_SVG_MAX_BYTES: {}", e))?,
+            Err(_) => DEFAULT_MAX_SVG_BYTES,
+        };
+        Ok(SvgConfig { sources, max_bytes })
+    }
+}
+
+/// Produce a sanitized SVG with the first source that succeeds, returning it with that source.
+///
+/// `ask_llm` sends a system message and a user message to the LLM and returns its answer.
+pub fn generate(
+    config: &SvgConfig,
+    sd_prompt: &str,
+    params: &SdParams,
+    art: &ProceduralArt,
+    ask_llm: impl Fn(String, &str) -> Result<String, String>,
+) -> Result<(String, SvgSource), String> {
+    let mut errors = Vec::new();
+    for &source in &config.sources {
+        let svg = match source {
+            SvgSource::Llm => {
+                let system = format!(
+                    "You are an autonomous artist drawing in SVG. Draw the scene described by the input text. Output ONLY a single SVG document: an <svg> element with xmlns=\"{}\" and viewBox=\"0 0 {} {}\", using only shapes, paths and gradients. Do not use text, scripts, images, links, CSS or external references. Keep it under {} bytes.",
+                    SVG_NAMESPACE, params.width, params.height, config.max_bytes
+                );
+                ask_llm(system, sd_prompt).and_then(|answer| extract(&answer).map(str::to_string))
+            }
+            SvgSource::Procedural => Ok(art.render_svg(params.seed, params.width, params.height)),
+        };
+        match svg.and_then(|svg| sanitize(&svg, config.max_bytes)) {
+            Ok(svg) => return Ok((svg, source)),
+            Err(e) => {
+                eprintln!("SVG from {} rejected: {}", source.name(), e);
+                errors.push(format!("{}: {}", source.name(), e));
+            }
+        }
+    }
+    Err(format!("No SVG within the budget. {}", errors.join("; ")))
+}
+
+/// The SVG document in an LLM answer, which may wrap it in prose or a code block
+pub fn extract(answer: &str) -> Result<&str, String> {
+    let start = answer.find("<svg").ok_or("Answer holds no SVG document")?;
+    let end = answer.rfind("</svg>").ok_or("Answer holds an unterminated SVG document")?;
+    if end < start {
+        return Err("Answer holds no SVG document".to_string());
+    }
+    Ok(&answer[start..end + "</svg>".len()])
+}
+
+/// Rebuild an SVG from its allowed elements and attributes, dropping comments,
+/// whitespace and everything that could run code or load other resources.
+///
+/// Scripts, `foreignObject`, `image`, `use`, styles, event handlers and links are
+/// removed, and `url()` references may only point inside the document. The result
+/// is minified and fails when it exceeds `max_bytes` or draws nothing.
+pub fn sanitize(svg: &str, max_bytes: usize) -> Result<String, String> {
+    // DTDs are rejected by the parser, so entities cannot expand
+    let document =
+        roxmltree::Document::parse(svg).map_err(|e| format!("SVG does not parse: {}", e))?;
+    let root = document.root_element();
+    if root.tag_name().name() != "svg"
+        || root.tag_name().namespace().is_some_and(|namespace| namespace != SVG_NAMESPACE)
+    {
+        return Err("Document is not an SVG".to_string());
+    }
+
+    let mut content = String::new();
+    write_children(root, &mut content);
+    if content.is_empty() {
+        return Err("SVG draws nothing".to_string());
+    }
+
+    let mut out = format!("<svg xmlns=\"{}\"", SVG_NAMESPACE);
+    write_attributes(root, &mut out);
+    out.push('>');
+    out.push_str(&content);
+    out.push_str("</svg>");
+
+    if out.len() > max_bytes {
+        return Err(format!("SVG of {} bytes exceeds the budget of {}", out.len(), max_bytes));
+    }
+    Ok(out)
+}
+
+fn write_children(node: roxmltree::Node, out: &mut String) {
+    for child in node.children() {
+        if child.is_text() {
+            if TEXT_ELEMENTS.contains(&node.tag_name().name()) {
+                let text = collapse(child.text().unwrap_or_default());
+                out.push_str(&escape(&text));
+            }
+            continue;
+        }
+        let name = child.tag_name().name();
+        let foreign = child.tag_name().namespace().is_some_and(|ns| ns != SVG_NAMESPACE);
+        if !child.is_element() || foreign || !ELEMENTS.contains(&name) {
+            continue;
+        }
+
+        out.push('<');
+        out.push_str(name);
+        write_attributes(child, out);
+        let mut content = String::new();
+        write_children(child, &mut content);
+        if content.is_empty() {
+            out.push_str("/>");
+        } else {
+            out.push('>');
+            out.push_str(&content);
+            out.push_str("</");
+            out.push_str(name);
+            out.push('>');
+        }
+    }
+}
+
+fn write_attributes(node: roxmltree::Node, out: &mut String) {
+    for attribute in node.attributes() {
+        // Namespaced attributes such as xlink:href are links or metadata
+        if attribute.namespace().is_some() || !ATTRIBUTES.contains(&attribute.name()) {
+            continue;
+        }
+        let value = collapse(attribute.value());
+        if !is_safe_value(&value) {
+            continue;
+        }
+        out.push(' ');
+        out.push_str(attribute.name());
+        out.push_str("=\"");
+        out.push_str(&escape(&value));
+        out.push('"');
+    }
+}
+
+/// Whether an attribute value stays inside the document
+fn is_safe_value(value: &str) -> bool {
+    let value = value.to_ascii_lowercase();
+    let local_urls = value.match_indices("url(").all(|(i, _)| {
+        value[i + "url(".len()..].trim_start().trim_start_matches(['"', '\'']).starts_with('#')
+    });
+    local_urls && !["javascript:", "data:", "expression("].iter().any(|bad| value.contains(bad))
+}
+
+fn collapse(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_active_content() {
+        let svg = r##"<?xml version="1.0"?>
+            <!-- drawn by hand -->
+            <svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink"
+                 viewBox="0 0 10 10" onload="alert(1)">
+              <script>alert(1)</script>
+              <style>rect { fill: url(https://example.com/x) }</style>
+              <foreignObject><div xmlns="http://www.w3.org/1999/xhtml">hi</div></foreignObject>
+              <image xlink:href="https://example.com/x.png" width="10" height="10"/>
+              <a href="https://example.com"><rect width="1" height="1"/></a>
+              <rect   width="10"  height="10" fill="url(#g)" onclick="x()" style="fill:red"/>
+              <circle cx="5" cy="5" r="2" fill="url(https://example.com/#g)" stroke="red"/>
+              <text x="1" y="9" fill="black">  a &amp; b  </text>
+            </svg>"##;
+        assert_eq!(
+            sanitize(svg, 1024).unwrap(),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 10 10\">\
+             <rect width=\"10\" height=\"10\" fill=\"url(#g)\"/>\
+             <circle cx=\"5\" cy=\"5\" r=\"2\" stroke=\"red\"/>\
+             <text x=\"1\" y=\"9\" fill=\"black\">a &amp; b</text></svg>"
+        );
+    }
+
+    #[test]
+    fn rejects_documents_outside_the_rules() {
+        let doctype = r#"<!DOCTYPE svg [<!ENTITY x "y">]><svg xmlns="http://www.w3.org/2000/svg"><rect width="1" height="1"/></svg>"#;
+        assert!(sanitize(doctype, 1024).is_err());
+        assert!(sanitize("<html><body/></html>", 1024).is_err());
+        assert_eq!(
+            sanitize(r#"<svg><script>alert(1)</script></svg>"#, 1024),
+            Err("SVG draws nothing".to_string())
+        );
+
+        let svg = r#"<svg viewBox="0 0 10 10"><rect width="10" height="10" fill="red"/></svg>"#;
+        let budget = sanitize(svg, 1024).unwrap().len();
+        assert!(sanitize(svg, budget).is_ok());
+        assert_eq!(
+            sanitize(svg, budget - 1),
+            Err(format!("SVG of {} bytes exceeds the budget of {}", budget, budget - 1))
+        );
+    }
+
+    #[test]
+    fn extracts_svg_from_answers() {
+        let answer = "Here is your art:\n```svg\n<svg viewBox=\"0 0 1 1\"></svg>\n```";
+        assert_eq!(extract(answer), Ok("<svg viewBox=\"0 0 1 1\"></svg>"));
+        assert!(extract("I cannot draw that.").is_err());
+    }
+}
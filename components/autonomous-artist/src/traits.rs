@@ -0,0 +1,281 @@
+use crate::ipfs;
+use crate::nft::Attribute;
+use alloy_primitives::{keccak256, Address, B256};
+use serde::{Deserialize, Serialize};
+
+/// A built-in trait, as (trait type, [(value, weight, Stable Diffusion keywords)])
+type BuiltinTrait = (&'static str, &'static [(&'static str, u32, &'static str)]);
+
+const BUILTIN_TRAITS: [BuiltinTrait; 4] = [
+    (
+        "Background",
+        &[
+            ("Plain", 40, "plain studio backdrop"),
+            ("Gradient", 30, "soft gradient background"),
+            ("Cosmic", 15, "cosmic nebula background"),
+            ("Gilded", 10, "gold leaf background"),
+            ("Void", 5, "infinite black void background"),
+        ],
+    ),
+    (
+        "Frame",
+        &[
+            ("None", 50, ""),
+            ("Wooden", 25, "framed in carved wood"),
+            ("Ornate Gold", 15, "framed in an ornate gold frame"),
+            ("Neon", 10, "framed by glowing neon tubes"),
+        ],
+    ),
+    (
+        "Mood",
+        &[
+            ("Serene", 35, "serene, calm atmosphere"),
+            ("Melancholic", 25, "melancholic, wistful atmosphere"),
+            ("Joyful", 20, "joyful, playful atmosphere"),
+            ("Ominous", 15, "ominous, foreboding atmosphere"),
+            ("Ecstatic", 5, "ecstatic, euphoric atmosphere"),
+        ],
+    ),
+    (
+        "Palette",
+        &[
+            ("vivid", 30, "vivid color palette"),
+            ("pastel", 25, "pastel color palette"),
+            ("earth", 20, "earth color palette"),
+            ("monochrome", 10, "monochrome color palette"),
+            ("neon", 10, "neon color palette"),
+            ("sepia", 5, "sepia color palette"),
+        ],
+    ),
+];
+
+/// One possible value of a trait, drawn with probability `weight / total weight`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TraitOption {
+    pub value: String,
+    pub weight: u32,
+    /// Keywords appended to the Stable Diffusion prompt, empty for none
+    #[serde(default)]
+    pub keywords: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TraitCategory {
+    pub trait_type: String,
+    pub options: Vec<TraitOption>,
+}
+
+/// A drawn trait
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SampledTrait {
+    pub trait_type: String,
+    pub value: String,
+    pub keywords: String,
+}
+
+impl SampledTrait {
+    pub fn attribute(&self) -> Attribute {
+        Attribute::new(self.trait_type.clone(), self.value.clone())
+    }
+}
+
+/// Weighted trait table for rarity tiers.
+///
+/// The table is read from `WAVS_ENV_TRAIT_TABLE`, either inline JSON or a URI
+/// pointing to it, and falls back to the built-in table. Every operator must
+/// draw the same traits, so sampling only depends on the seed and the table.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TraitTable {
+    pub categories: Vec<TraitCategory>,
+}
+
+impl Default for TraitTable {
+    fn default() -> Self {
+        let categories = BUILTIN_TRAITS
+            .iter()
+            .map(|(trait_type, options)| TraitCategory {
+                trait_type: trait_type.to_string(),
+                options: options
+                    .iter()
+                    .map(|(value, weight, keywords)| TraitOption {
+                        value: value.to_string(),
+                        weight: *weight,
+                        keywords: keywords.to_string(),
+                    })
+                    .collect(),
+            })
+            .collect();
+        TraitTable { categories }
+    }
+}
+
+impl TraitTable {
+    /// Load the trait table configured for this service
+    pub fn load() -> Result<Self, String> {
+        let table = match std::env::var("WAVS_ENV_TRAIT_TABLE") {
+            Ok(source) if !source.trim().is_empty() => {
+                let json = ipfs::fetch_json_config(&source)
+                    .map_err(|e| format!("Failed to fetch trait table: {}", e))?;
+                serde_json::from_slice(&json)
+                    .map_err(|e| format!("Failed to parse trait table: {}", e))?
+            }
+            _ => Self::default(),
+        };
+        table.validate()?;
+        Ok(table)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (i, category) in self.categories.iter().enumerate() {
+            if category.trait_type.trim().is_empty() {
+                return Err("Trait type must not be empty".to_string());
+            }
+            if self.categories[..i].iter().any(|other| other.trait_type == category.trait_type) {
+                return Err(format!("Duplicate trait type: {}", category.trait_type));
+            }
+            let total: u64 = category.options.iter().map(|option| u64::from(option.weight)).sum();
+            if total == 0 {
+                return Err(format!("Trait {} has no options with weight", category.trait_type));
+            }
+        }
+        Ok(())
+    }
+
+    /// Draw one option per category.
+    ///
+    /// Each category is drawn from its own hash of the seed, so adding or
+    /// skipping a category does not change the others.
+    pub fn sample(&self, seed: B256) -> Vec<SampledTrait> {
+        self.categories
+            .iter()
+            .map(|category| {
+                let mut data = seed.to_vec();
+                data.extend_from_slice(category.trait_type.as_bytes());
+                let hash = keccak256(data);
+                let total: u64 =
+                    category.options.iter().map(|option| u64::from(option.weight)).sum();
+                // The modulo bias of a 64-bit draw over a u32 range is negligible
+                let mut roll = u64::from_be_bytes(hash[..8].try_into().unwrap()) % total;
+
+                let option = category
+                    .options
+                    .iter()
+                    .find(|option| {
+                        let weight = u64::from(option.weight);
+                        if roll < weight {
+                            return true;
+                        }
+                        roll -= weight;
+                        false
+                    })
+                    .expect("roll is below the total weight");
+
+                SampledTrait {
+                    trait_type: category.trait_type.clone(),
+                    value: option.value.clone(),
+                    keywords: option.keywords.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Sampling seed of a trigger, unique per trigger contract and trigger id
+pub fn trigger_seed(contract: Address, trigger_id: u64) -> B256 {
+    let mut data = contract.to_vec();
+    data.extend_from_slice(&trigger_id.to_be_bytes());
+    keccak256(data)
+}
+
+/// Keywords of the drawn traits, appended to the Stable Diffusion prompt
+pub fn sd_prompt_suffix(traits: &[SampledTrait]) -> String {
+    traits.iter().filter(|t| !t.keywords.is_empty()).map(|t| format!(", {}", t.keywords)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const SAMPLES: u64 = 10_000;
+
+    fn counts(table: &TraitTable, trait_type: &str) -> HashMap<String, u64> {
+        let contract = Address::repeat_byte(0x42);
+        let mut counts = HashMap::new();
+        for trigger_id in 0..SAMPLES {
+            let traits = table.sample(trigger_seed(contract, trigger_id));
+            let drawn = traits.iter().find(|t| t.trait_type == trait_type).unwrap();
+            *counts.entry(drawn.value.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn is_deterministic() {
+        let table = TraitTable::default();
+        let seed = trigger_seed(Address::ZERO, 7);
+        assert_eq!(table.sample(seed), table.sample(seed));
+        assert_eq!(table.sample(seed).len(), BUILTIN_TRAITS.len());
+    }
+
+    #[test]
+    fn follows_the_weights() {
+        let table = TraitTable::default();
+        for category in &table.categories {
+            let counts = counts(&table, &category.trait_type);
+            let total: u64 = category.options.iter().map(|o| u64::from(o.weight)).sum();
+            for option in &category.options {
+                let expected = SAMPLES as f64 * option.weight as f64 / total as f64;
+                let actual = *counts.get(&option.value).unwrap_or(&0) as f64;
+                // Within 5 standard deviations of the binomial mean
+                let p = option.weight as f64 / total as f64;
+                let tolerance = 5.0 * (SAMPLES as f64 * p * (1.0 - p)).sqrt();
+                assert!(
+                    (actual - expected).abs() <= tolerance,
+                    "{} {}: drew {} times, expected {} +/- {}",
+                    category.trait_type,
+                    option.value,
+                    actual,
+                    expected,
+                    tolerance
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn never_draws_zero_weight() {
+        let table: TraitTable = serde_json::from_str(
+            r#"{"categories":[{"trait_type":"Halo","options":[
+                {"value":"Never","weight":0},
+                {"value":"Common","weight":9},
+                {"value":"Rare","weight":1}
+            ]}]}"#,
+        )
+        .unwrap();
+        let counts = counts(&table, "Halo");
+        assert_eq!(counts.get("Never"), None);
+        assert!(counts["Common"] > counts["Rare"]);
+    }
+
+    #[test]
+    fn categories_are_independent() {
+        let full = TraitTable::default();
+        let mut partial = full.clone();
+        partial.categories.remove(0);
+
+        let seed = trigger_seed(Address::ZERO, 1);
+        assert_eq!(full.sample(seed)[1..], partial.sample(seed)[..]);
+    }
+
+    #[test]
+    fn rejects_invalid_tables() {
+        let mut table = TraitTable::default();
+        table.categories[0].options.iter_mut().for_each(|o| o.weight = 0);
+        assert!(table.validate().is_err());
+
+        let mut table = TraitTable::default();
+        table.categories.push(table.categories[0].clone());
+        assert!(table.validate().is_err());
+    }
+}
@@ -0,0 +1,97 @@
+use alloy_primitives::keccak256;
+use serde::Serialize;
+
+/// Digest of a single intermediate artifact
+#[derive(Serialize, Debug, Clone)]
+pub struct Stage {
+    pub name: String,
+    pub digest: String,
+}
+
+/// Summary embedded in the NFT metadata
+#[derive(Serialize, Debug, Clone)]
+pub struct TranscriptSummary {
+    pub digest: String,
+    pub stages: Vec<Stage>,
+}
+
+/// Digests of every intermediate artifact produced while generating a token.
+///
+/// Operators must produce identical output for aggregation, comparing the
+/// logged stages of two operators shows the first stage where they diverged.
+#[derive(Default, Debug)]
+pub struct Transcript {
+    stages: Vec<Stage>,
+}
+
+impl Transcript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hash an artifact and append it to the transcript
+    pub fn record(&mut self, name: &str, data: &[u8]) {
+        let digest = keccak256(data).to_string();
+        eprintln!("Transcript stage {}: {}", name, digest);
+        self.stages.push(Stage { name: name.to_string(), digest });
+    }
+
+    /// Digest over all stages recorded so far, in order.
+    ///
+    /// Each stage name is prefixed with its length, so two different stage lists
+    /// cannot concatenate to the same bytes.
+    pub fn digest(&self) -> String {
+        let mut data = Vec::new();
+        for stage in &self.stages {
+            data.extend_from_slice(&(stage.name.len() as u64).to_be_bytes());
+            data.extend_from_slice(stage.name.as_bytes());
+            data.extend_from_slice(stage.digest.as_bytes());
+        }
+        keccak256(data).to_string()
+    }
+
+    pub fn summary(&self) -> TranscriptSummary {
+        TranscriptSummary { digest: self.digest(), stages: self.stages.clone() }
+    }
+}
+
+/// Whether the transcript summary should be embedded in the NFT metadata
+pub fn embed_enabled() -> bool {
+    std::env::var("WAVS_ENV_EMBED_TRANSCRIPT").map(|v| v == "true" || v == "1").unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_stage_digests_in_order() {
+        let mut transcript = Transcript::new();
+        transcript.record("prompt", b"a lighthouse");
+        transcript.record("image", &[0x89, 0x50]);
+
+        let summary = transcript.summary();
+        let stages: Vec<_> = summary.stages.iter().map(|stage| stage.name.as_str()).collect();
+        assert_eq!(stages, ["prompt", "image"]);
+        assert_eq!(summary.stages[0].digest, keccak256(b"a lighthouse").to_string());
+        assert_eq!(summary.digest, transcript.digest());
+
+        let mut swapped = Transcript::new();
+        swapped.record("image", &[0x89, 0x50]);
+        swapped.record("prompt", b"a lighthouse");
+        assert_ne!(swapped.digest(), transcript.digest());
+    }
+
+    #[test]
+    fn separates_stage_names() {
+        // Without a length prefix both would hash "ab" followed by the digest
+        let digest = keccak256(b"").to_string();
+        let stage = |name: &str, digest: String| Transcript {
+            stages: vec![Stage { name: name.to_string(), digest }],
+        };
+        assert_ne!(
+            stage("ab", digest.clone()).digest(),
+            stage("a", format!("b{}", digest)).digest()
+        );
+    }
+}
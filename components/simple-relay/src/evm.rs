@@ -0,0 +1,186 @@
+use crate::bindings::host::get_eth_chain_config;
+use alloy_network::Ethereum;
+use alloy_primitives::{Address, Bytes, LogData, TxKind, B256, U256};
+use alloy_provider::{Provider, RootProvider};
+use alloy_rpc_types::{BlockId, Filter, TransactionInput};
+use alloy_sol_types::{sol, SolCall};
+use wavs_wasi_chain::ethereum::new_eth_provider;
+use wstd::runtime::block_on;
+
+sol! {
+    interface IERC721 {
+        function ownerOf(uint256 tokenId) external view returns (address);
+        function tokenURI(uint256 tokenId) external view returns (string);
+    }
+
+    interface IWavsMinter {
+        struct Receipt {
+            address creator;
+            string prompt;
+            uint8 wavsTriggerType;
+            bool fulfilled;
+            uint256 tokenId;
+            string tokenURI;
+        }
+
+        struct UpdateReceipt {
+            uint256 tokenId;
+            string tokenURI;
+            bool fulfilled;
+        }
+
+        function getTrigger(uint64 triggerId) external view returns (Receipt memory);
+        function getUpdateReceipt(uint64 triggerId) external view returns (UpdateReceipt memory);
+    }
+}
+
+pub use IWavsMinter::{Receipt, UpdateReceipt};
+
+/// Read-only calls against one of the chains WAVS is configured for
+pub struct Chain {
+    pub chain_id: U256,
+    endpoint: String,
+}
+
+/// Where an event was emitted within its chain
+pub struct LogCoordinates {
+    pub block_hash: B256,
+    pub transaction_hash: B256,
+    pub log_index: u64,
+    /// Events of the same signature and contract emitted before it in the same transaction
+    pub earlier_in_transaction: Vec<LogData>,
+}
+
+impl Chain {
+    pub fn new(chain_name: &str) -> Result<Self, String> {
+        let chain_config = get_eth_chain_config(chain_name)
+            .ok_or_else(|| format!("Unknown chain: {}", chain_name))?;
+        let endpoint = chain_config
+            .http_endpoint
+            .ok_or_else(|| format!("No HTTP endpoint configured for chain: {}", chain_name))?;
+        let chain_id = chain_config
+            .chain_id
+            .parse()
+            .map_err(|e| format!("Invalid chain id of {}: {}", chain_name, e))?;
+        Ok(Chain { chain_id, endpoint })
+    }
+
+    fn call(&self, to: Address, input: Vec<u8>, block: BlockId) -> Result<Bytes, String> {
+        let endpoint = self.endpoint.clone();
+        block_on(async move {
+            let provider: RootProvider<Ethereum> = new_eth_provider::<Ethereum>(endpoint);
+            let tx = alloy_rpc_types::eth::TransactionRequest {
+                to: Some(TxKind::Call(to)),
+                input: TransactionInput { input: Some(input.into()), data: None },
+                ..Default::default()
+            };
+            provider.call(&tx).block(block).await.map_err(|e| e.to_string())
+        })
+    }
+
+    /// Find an event the trigger delivered by its contents, as WAVS only passes on its block
+    pub fn log_coordinates(
+        &self,
+        address: Address,
+        block_number: u64,
+        topics: &[B256],
+        data: &[u8],
+    ) -> Result<LogCoordinates, String> {
+        let signature = *topics.first().ok_or("Event log has no topics")?;
+        let filter = Filter::new()
+            .address(address)
+            .from_block(block_number)
+            .to_block(block_number)
+            .event_signature(signature);
+        let endpoint = self.endpoint.clone();
+        let logs = block_on(async move {
+            let provider: RootProvider<Ethereum> = new_eth_provider::<Ethereum>(endpoint);
+            provider.get_logs(&filter).await.map_err(|e| e.to_string())
+        })?;
+
+        let log = logs
+            .iter()
+            .find(|log| log.inner.data.topics() == topics && log.inner.data.data[..] == data[..])
+            .ok_or_else(|| format!("Event not found in block {}", block_number))?;
+        let transaction_hash = log.transaction_hash.ok_or("Event log has no transaction hash")?;
+        let log_index = log.log_index.ok_or("Event log has no index")?;
+        let earlier_in_transaction = logs
+            .iter()
+            .filter(|other| {
+                other.transaction_hash == Some(transaction_hash)
+                    && other.log_index.is_some_and(|index| index < log_index)
+            })
+            .map(|other| other.inner.data.clone())
+            .collect();
+        Ok(LogCoordinates {
+            block_hash: log.block_hash.ok_or("Event log has no block hash")?,
+            transaction_hash,
+            log_index,
+            earlier_in_transaction,
+        })
+    }
+
+    /// Owner of a token at a block, an error if the token does not exist
+    pub fn owner_of(
+        &self,
+        nft_contract: Address,
+        token_id: U256,
+        block_number: u64,
+    ) -> Result<Address, String> {
+        let result = self
+            .call(
+                nft_contract,
+                IERC721::ownerOfCall { tokenId: token_id }.abi_encode(),
+                block_number.into(),
+            )
+            .map_err(|e| format!("Token {} does not exist: {}", token_id, e))?;
+        let decoded = IERC721::ownerOfCall::abi_decode_returns(&result, true)
+            .map_err(|e| format!("Failed to decode ownerOf: {}", e))?;
+        Ok(decoded._0)
+    }
+
+    /// URI of a token at a block
+    pub fn token_uri(
+        &self,
+        nft_contract: Address,
+        token_id: U256,
+        block_number: u64,
+    ) -> Result<String, String> {
+        let result = self.call(
+            nft_contract,
+            IERC721::tokenURICall { tokenId: token_id }.abi_encode(),
+            block_number.into(),
+        )?;
+        let decoded = IERC721::tokenURICall::abi_decode_returns(&result, true)
+            .map_err(|e| format!("Failed to decode tokenURI: {}", e))?;
+        Ok(decoded._0)
+    }
+
+    /// The minter's receipt of a trigger, with a zero creator if it does not exist
+    pub fn receipt(&self, minter_contract: Address, trigger_id: u64) -> Result<Receipt, String> {
+        let result = self.call(
+            minter_contract,
+            IWavsMinter::getTriggerCall { triggerId: trigger_id }.abi_encode(),
+            BlockId::latest(),
+        )?;
+        let decoded = IWavsMinter::getTriggerCall::abi_decode_returns(&result, true)
+            .map_err(|e| format!("Failed to decode receipt: {}", e))?;
+        Ok(decoded._0)
+    }
+
+    /// The minter's record of an update trigger of the NFT contract, unfulfilled if it does not exist
+    pub fn update_receipt(
+        &self,
+        minter_contract: Address,
+        trigger_id: u64,
+    ) -> Result<UpdateReceipt, String> {
+        let result = self.call(
+            minter_contract,
+            IWavsMinter::getUpdateReceiptCall { triggerId: trigger_id }.abi_encode(),
+            BlockId::latest(),
+        )?;
+        let decoded = IWavsMinter::getUpdateReceiptCall::abi_decode_returns(&result, true)
+            .map_err(|e| format!("Failed to decode update receipt: {}", e))?;
+        Ok(decoded._0)
+    }
+}
@@ -1,37 +1,168 @@
 #[allow(warnings)]
 mod bindings;
+mod evm;
+
+use alloy_primitives::{Address, B256, U256};
 use alloy_sol_macro::sol;
-use alloy_sol_types::SolValue;
+use alloy_sol_types::{SolEvent, SolValue};
 use bindings::{
     export,
     wavs::worker::layer_types::{TriggerData, TriggerDataEthContractEvent},
     Guest, TriggerAction,
 };
+use evm::Chain;
+use std::str::FromStr;
 use wavs_wasi_chain::decode_event_log_data;
 
 // Define Solidity types.
 sol!("../../src/interfaces/IWavsNftServiceTypes.sol");
 
 // Import solidity types for decoding / encoding.
-use crate::IWavsNftServiceTypes::WavsNftMint;
+use crate::IWavsNftServiceTypes::{
+    WavsFulfillment, WavsLogOrigin, WavsNftMint, WavsNftUpdate, WavsTriggerType,
+};
 struct Component;
 
 impl Guest for Component {
     fn run(trigger_action: TriggerAction) -> std::result::Result<Option<Vec<u8>>, String> {
         match trigger_action.data {
-            TriggerData::EthContractEvent(TriggerDataEthContractEvent { log, .. }) => {
-                // Decode the NewTrigger event to get the _triggerInfo bytes
-                let WavsNftMint { triggerId, .. } = decode_event_log_data!(log)
-                    .map_err(|e| format!("Failed to decode event log data: {}", e))?;
+            TriggerData::EthContractEvent(TriggerDataEthContractEvent {
+                contract_address,
+                chain_name,
+                log,
+                block_height,
+            }) => {
+                let nft_contract = config_address("nft_contract")?;
+                let minter_contract = config_address("minter_contract")?;
+                // The minter can live on another chain than the NFT contract
+                let minter_chain =
+                    std::env::var("minter_chain").unwrap_or_else(|_| chain_name.clone());
+
+                // Only events of the configured collection can fulfil triggers
+                let emitter = Address::from_slice(&contract_address.raw_bytes);
+                if emitter != nft_contract {
+                    return Err(format!(
+                        "Event emitted by {} instead of the NFT contract {}",
+                        emitter, nft_contract
+                    ));
+                }
+
+                // The first topic is the signature of the event
+                let topics: Vec<B256> =
+                    log.topics.iter().map(|topic| B256::from_slice(topic)).collect();
+                let signature = *topics.first().ok_or("Event log has no topics")?;
+
+                // Identify the event across chains for the minter's replay protection
+                let chain = Chain::new(&chain_name)?;
+                let coordinates =
+                    chain.log_coordinates(nft_contract, block_height, &topics, &log.data)?;
+                let origin = WavsLogOrigin {
+                    chainId: chain.chain_id,
+                    blockHash: coordinates.block_hash,
+                    transactionHash: coordinates.transaction_hash,
+                    logIndex: U256::from(coordinates.log_index),
+                };
 
-                eprintln!("Fulfilling Trigger ID: {}", triggerId);
+                let (owner, fulfillment) = if signature == WavsNftMint::SIGNATURE_HASH {
+                    let WavsNftMint { to, tokenId, tokenURI, triggerId } =
+                        decode_event_log_data!(log)
+                            .map_err(|e| format!("Failed to decode event log data: {}", e))?;
+                    let fulfillment = WavsFulfillment {
+                        triggerId,
+                        kind: WavsTriggerType::MINT,
+                        tokenId,
+                        tokenURI,
+                        origin,
+                    };
+                    (to, fulfillment)
+                } else if signature == WavsNftUpdate::SIGNATURE_HASH {
+                    let WavsNftUpdate { owner, tokenId, tokenURI, triggerId } =
+                        decode_event_log_data!(log)
+                            .map_err(|e| format!("Failed to decode event log data: {}", e))?;
+                    let fulfillment = WavsFulfillment {
+                        triggerId,
+                        kind: WavsTriggerType::UPDATE,
+                        tokenId,
+                        tokenURI,
+                        origin,
+                    };
+                    (owner, fulfillment)
+                } else {
+                    return Err(format!("Unsupported event: {}", signature));
+                };
+                let WavsFulfillment { triggerId, tokenId, .. } = fulfillment;
 
-                // Return the ABI-encoded triggerId
-                Ok(Some(triggerId.abi_encode()))
+                // The token must be on chain as the event describes it, at the event's block as a
+                // later transfer or update must not fail the relay of an earlier event
+                let token_owner = chain.owner_of(nft_contract, tokenId, block_height)?;
+                if token_owner != owner {
+                    return Err(format!(
+                        "Token {} is owned by {}, not {}",
+                        tokenId, token_owner, owner
+                    ));
+                }
+                let token_uri = chain.token_uri(nft_contract, tokenId, block_height)?;
+                if token_uri != fulfillment.tokenURI {
+                    return Err(format!(
+                        "Token {} has URI {}, not {}",
+                        tokenId, token_uri, fulfillment.tokenURI
+                    ));
+                }
+
+                // Replays and the extra tokens of a batch mint need no transaction
+                let minter =
+                    if minter_chain == chain_name { chain } else { Chain::new(&minter_chain)? };
+                let fulfilled = match fulfillment.kind {
+                    WavsTriggerType::MINT => {
+                        // The receipt must exist and be waiting for a mint
+                        let receipt = minter.receipt(minter_contract, triggerId)?;
+                        if receipt.creator == Address::ZERO {
+                            return Err(format!("No receipt for Trigger ID: {}", triggerId));
+                        }
+                        if receipt.wavsTriggerType != WavsTriggerType::MINT as u8
+                            && receipt.wavsTriggerType != WavsTriggerType::BATCH_MINT as u8
+                        {
+                            return Err(format!("Trigger ID {} is not a mint", triggerId));
+                        }
+                        // `WavsNft` mints a whole batch in one transaction, only its first token
+                        // fulfils the receipt as relaying the others would revert
+                        let later_in_batch = receipt.wavsTriggerType
+                            == WavsTriggerType::BATCH_MINT as u8
+                            && coordinates.earlier_in_transaction.iter().any(|earlier| {
+                                WavsNftMint::decode_log_data(earlier, true)
+                                    .is_ok_and(|mint| mint.triggerId == triggerId)
+                            });
+                        if later_in_batch {
+                            eprintln!(
+                                "Token {} is not the first of batch Trigger ID {}, skipping",
+                                tokenId, triggerId
+                            );
+                            return Ok(None);
+                        }
+                        receipt.fulfilled
+                    }
+                    _ => minter.update_receipt(minter_contract, triggerId)?.fulfilled,
+                };
+                if fulfilled {
+                    eprintln!("Trigger ID {} is already fulfilled, skipping", triggerId);
+                    return Ok(None);
+                }
+
+                eprintln!("Fulfilling Trigger ID: {} with token {}", triggerId, tokenId);
+
+                // Return the ABI-encoded fulfillment
+                Ok(Some(fulfillment.abi_encode()))
             }
             _ => Err("Unsupported trigger data".to_string()),
         }
     }
 }
 
+/// Contract address from the service config
+fn config_address(name: &str) -> Result<Address, String> {
+    let address =
+        std::env::var(name).map_err(|e| format!("Failed to get {} from config: {}", name, e))?;
+    Address::from_str(&address).map_err(|e| format!("Invalid {}: {}", name, e))
+}
+
 export!(Component with_types_in bindings);
@@ -0,0 +1,95 @@
+//! The IPFS `add` request of the autonomous-artist component, shared with the host
+//! tools so files pinned again elsewhere are uploaded exactly as the component does.
+//!
+//! Only the request is built here, sending it is left to the caller's HTTP client:
+//! `wstd` in the component and `reqwest` on the host.
+
+/// Boundary between the parts of the multipart form
+pub const BOUNDARY: &str = "----RustBoundary";
+
+/// A multipart form uploading one file to an IPFS `add` endpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddRequest {
+    /// Value of the request's `Content-Type` header
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+impl AddRequest {
+    /// Form with `content` as its `file` part, sent as `content_type`
+    pub fn new(filename: &str, content_type: &str, content: &[u8]) -> Self {
+        let mut body = format!(
+            "--{}\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n\
+            Content-Type: {}\r\n\r\n",
+            BOUNDARY, filename, content_type
+        )
+        .into_bytes();
+        body.extend_from_slice(content);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+
+        AddRequest { content_type: format!("multipart/form-data; boundary={}", BOUNDARY), body }
+    }
+}
+
+/// Extension of the files uploaded as `content_type`, `bin` for unknown types
+pub fn extension(content_type: &str) -> &'static str {
+    match content_type {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "application/json" => "json",
+        _ => "bin",
+    }
+}
+
+/// MIME type of a file from its extension, `application/octet-stream` for unknown ones
+pub fn content_type(filename: &str) -> &'static str {
+    let extension = filename.rsplit_once('.').map(|(_, extension)| extension).unwrap_or_default();
+    match extension.to_ascii_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "json" => "application/json",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_multipart_forms() {
+        let request = AddRequest::new("nft_image.png", "image/png", b"\x89PNG");
+        assert_eq!(request.content_type, "multipart/form-data; boundary=----RustBoundary");
+        assert_eq!(
+            request.body,
+            b"------RustBoundary\r\n\
+            Content-Disposition: form-data; name=\"file\"; filename=\"nft_image.png\"\r\n\
+            Content-Type: image/png\r\n\r\n\
+            \x89PNG\r\n\
+            ------RustBoundary--\r\n"
+        );
+    }
+
+    #[test]
+    fn maps_content_types_and_extensions() {
+        for content_type in ["image/png", "image/jpeg", "image/webp", "application/json"] {
+            assert_eq!(
+                super::content_type(&format!("file.{}", extension(content_type))),
+                content_type
+            );
+        }
+        assert_eq!(extension("text/html"), "bin");
+        assert_eq!(
+            content_type("QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG"),
+            "application/octet-stream"
+        );
+        assert_eq!(content_type("IMAGE.JPEG"), "image/jpeg");
+    }
+}
@@ -37,6 +37,10 @@ contract Deploy is Common {
             vm.parseAddress(_serviceManagerAddr)
         );
 
+        // Let the minter mint placeholder NFTs for reveal mints
+        nft.grantRole(nft.MINTER_ROLE(), address(minter));
+        minter.setNft(address(nft));
+
         vm.stopBroadcast();
 
         // Log the deployments
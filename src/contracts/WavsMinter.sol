@@ -4,6 +4,7 @@ pragma solidity ^0.8.22;
 import "@openzeppelin/contracts/access/Ownable.sol";
 import "@openzeppelin/contracts/utils/ReentrancyGuard.sol";
 import {IWavsNftServiceTypes} from "interfaces/IWavsNftServiceTypes.sol";
+import {WavsNft} from "contracts/WavsNft.sol";
 import {IWavsServiceHandler} from "@wavs/interfaces/IWavsServiceHandler.sol";
 import {IWavsServiceManager} from "@wavs/interfaces/IWavsServiceManager.sol";
 
@@ -15,12 +16,28 @@ contract WavsMinter is Ownable, ReentrancyGuard, IWavsServiceHandler {
     // Config parameters
     uint256 public mintPrice = 0.1 ether;
 
+    // Maximum number of tokens a single batch mint can produce
+    uint256 public constant MAX_BATCH_SIZE = 10;
+
     // Mapping to store additional metadata for each trigger
     mapping(IWavsNftServiceTypes.TriggerId => Receipt) public receipts;
 
+    // Fulfilled updates, keyed by the trigger ID assigned by the NFT contract
+    mapping(IWavsNftServiceTypes.TriggerId => UpdateReceipt)
+        public updateReceipts;
+
     // Interface to the WAVS service manager
     IWavsServiceManager public serviceManager;
 
+    // NFT contract minting placeholder NFTs for reveal mints, needs the MINTER_ROLE
+    WavsNft public nft;
+
+    // Chain the NFT contract emits its events on, 0 accepts fulfillments from any chain
+    uint256 public nftChainId;
+
+    // Events already relayed, keyed by the hash of their WavsLogOrigin
+    mapping(bytes32 => bool) public relayedLogs;
+
     // Auto-incrementing trigger ID counter
     IWavsNftServiceTypes.TriggerId public nextTriggerId;
 
@@ -30,6 +47,16 @@ contract WavsMinter is Ownable, ReentrancyGuard, IWavsServiceHandler {
         string prompt;
         IWavsNftServiceTypes.WavsTriggerType wavsTriggerType;
         bool fulfilled;
+        // The token that fulfilled the trigger, the first one for batch mints
+        uint256 tokenId;
+        string tokenURI;
+    }
+
+    // Structure to hold the token an update was fulfilled with
+    struct UpdateReceipt {
+        uint256 tokenId;
+        string tokenURI;
+        bool fulfilled;
     }
 
     // Event emitted when a mint/update is triggered
@@ -38,15 +65,29 @@ contract WavsMinter is Ownable, ReentrancyGuard, IWavsServiceHandler {
         string prompt,
         uint64 indexed triggerId,
         uint8 wavsTriggerType,
-        uint256 tokenId
+        uint256 tokenId,
+        bytes options
     );
 
     // Event emitted when a mint is fulfilled
     event MintFulfilled(IWavsNftServiceTypes.TriggerId indexed triggerId);
 
+    // Event emitted when an update is fulfilled
+    event UpdateFulfilled(
+        IWavsNftServiceTypes.TriggerId indexed triggerId,
+        uint256 indexed tokenId,
+        string tokenURI
+    );
+
     // Event emitted when mint price is updated
     event MintPriceUpdated(uint256 newPrice);
 
+    // Event emitted when the NFT contract is updated
+    event NftUpdated(address nft);
+
+    // Event emitted when the chain of the NFT contract is updated
+    event NftChainIdUpdated(uint256 chainId);
+
     // Event emitted when fees are withdrawn
     event FeesWithdrawn(address indexed owner, uint256 amount);
 
@@ -65,9 +106,96 @@ contract WavsMinter is Ownable, ReentrancyGuard, IWavsServiceHandler {
     function triggerMint(
         string calldata prompt
     ) external payable nonReentrant returns (IWavsNftServiceTypes.TriggerId) {
-        // Clearer error message for insufficient payment
+        return
+            _triggerMint(
+                prompt,
+                "",
+                IWavsNftServiceTypes.WavsTriggerType.MINT,
+                mintPrice
+            );
+    }
+
+    /**
+     * @notice Trigger an AVS-generated NFT mint with mint-time parameters
+     * @param prompt The text prompt for AI generation
+     * @param options ABI-encoded IWavsNftServiceTypes.WavsMintOptions
+     */
+    function triggerMintWithOptions(
+        string calldata prompt,
+        bytes calldata options
+    ) external payable nonReentrant returns (IWavsNftServiceTypes.TriggerId) {
+        return
+            _triggerMint(
+                prompt,
+                options,
+                IWavsNftServiceTypes.WavsTriggerType.MINT,
+                mintPrice
+            );
+    }
+
+    /**
+     * @notice Trigger several AVS-generated NFT mints at once, paying the mint price for each
+     * @param prompt The text prompt for AI generation, used when the batch has no prompts
+     * @param batchOptions ABI-encoded IWavsNftServiceTypes.WavsBatchMintOptions
+     */
+    function triggerBatchMint(
+        string calldata prompt,
+        bytes calldata batchOptions
+    ) external payable nonReentrant returns (IWavsNftServiceTypes.TriggerId) {
+        IWavsNftServiceTypes.WavsBatchMintOptions memory batch = abi.decode(
+            batchOptions,
+            (IWavsNftServiceTypes.WavsBatchMintOptions)
+        );
+        require(batch.variants > 0, "Batch needs at least one variant");
+
+        uint256 prompts = batch.prompts.length == 0 ? 1 : batch.prompts.length;
+        uint256 size = prompts * batch.variants;
+        require(size <= MAX_BATCH_SIZE, "Batch too large");
+
+        return
+            _triggerMint(
+                prompt,
+                batchOptions,
+                IWavsNftServiceTypes.WavsTriggerType.BATCH_MINT,
+                mintPrice * size
+            );
+    }
+
+    /**
+     * @notice Mint an NFT immediately with a placeholder and have the AVS reveal its art later
+     * @dev The reveal is triggered and fulfilled by the NFT contract, no receipt is stored here
+     * @param prompt The text prompt for AI generation
+     * @param options ABI-encoded IWavsNftServiceTypes.WavsMintOptions, or empty for the defaults
+     * @return tokenId The ID of the minted NFT
+     */
+    function triggerRevealMint(
+        string calldata prompt,
+        bytes calldata options
+    ) external payable nonReentrant returns (uint256 tokenId) {
+        require(address(nft) != address(0), "Reveal mints are not enabled");
         require(msg.value >= mintPrice, "Insufficient payment: Please send at least the mint price");
 
+        tokenId = nft.mintPlaceholder(msg.sender, prompt, options);
+
+        // Refund any excess payment
+        uint256 excess = msg.value - mintPrice;
+        if (excess > 0) {
+            (bool refundSuccess, ) = payable(msg.sender).call{value: excess}(
+                ""
+            );
+            require(refundSuccess, "Failed to refund excess");
+        }
+    }
+
+    function _triggerMint(
+        string calldata prompt,
+        bytes memory options,
+        IWavsNftServiceTypes.WavsTriggerType wavsTriggerType,
+        uint256 price
+    ) internal returns (IWavsNftServiceTypes.TriggerId) {
+        // Clearer error message for insufficient payment
+        require(msg.value >= price, "Insufficient payment: Please send at least the mint price");
+
         // Get the next trigger ID and increment the counter
         IWavsNftServiceTypes.TriggerId triggerId = nextTriggerId;
         nextTriggerId = IWavsNftServiceTypes.TriggerId.wrap(
@@ -78,12 +206,14 @@ contract WavsMinter is Ownable, ReentrancyGuard, IWavsServiceHandler {
         receipts[triggerId] = Receipt({
             creator: msg.sender,
             prompt: prompt,
-            wavsTriggerType: IWavsNftServiceTypes.WavsTriggerType.MINT,
-            fulfilled: false
+            wavsTriggerType: wavsTriggerType,
+            fulfilled: false,
+            tokenId: 0,
+            tokenURI: ""
         });
 
         // Refund any excess payment
-        uint256 excess = msg.value - mintPrice;
+        uint256 excess = msg.value - price;
         if (excess > 0) {
             (bool refundSuccess, ) = payable(msg.sender).call{value: excess}(
                 ""
@@ -96,8 +226,9 @@ contract WavsMinter is Ownable, ReentrancyGuard, IWavsServiceHandler {
             msg.sender,
             prompt,
             IWavsNftServiceTypes.TriggerId.unwrap(triggerId),
-            uint8(IWavsNftServiceTypes.WavsTriggerType.MINT),
-            0 // tokenId is 0 for mints, the AVS ignores this value for minting
+            uint8(wavsTriggerType),
+            0, // tokenId is 0 for mints, the AVS ignores this value for minting
+            options
         );
 
         return triggerId;
@@ -114,23 +245,54 @@ contract WavsMinter is Ownable, ReentrancyGuard, IWavsServiceHandler {
     ) external override {
         serviceManager.validate(data, signature);
 
-        IWavsNftServiceTypes.TriggerId triggerId = abi.decode(
+        IWavsNftServiceTypes.WavsFulfillment memory fulfillment = abi.decode(
             data,
-            (IWavsNftServiceTypes.TriggerId)
+            (IWavsNftServiceTypes.WavsFulfillment)
         );
+        IWavsNftServiceTypes.TriggerId triggerId = fulfillment.triggerId;
 
-        // Check if the trigger exists and is not already fulfilled
+        // Accept each event once, from the chain of the NFT contract
         require(
-            receipts[triggerId].creator != address(0),
-            "Trigger does not exist"
+            nftChainId == 0 || fulfillment.origin.chainId == nftChainId,
+            "Event from another chain"
         );
-        require(!receipts[triggerId].fulfilled, "Trigger already fulfilled");
+        bytes32 logKey = keccak256(abi.encode(fulfillment.origin));
+        require(!relayedLogs[logKey], "Event already relayed");
+        relayedLogs[logKey] = true;
+
+        if (fulfillment.kind == IWavsNftServiceTypes.WavsTriggerType.MINT) {
+            Receipt storage receipt = receipts[triggerId];
+
+            // Check if the trigger exists and is not already fulfilled
+            require(receipt.creator != address(0), "Trigger does not exist");
+            require(!receipt.fulfilled, "Trigger already fulfilled");
 
-        // Mark the trigger as fulfilled
-        receipts[triggerId].fulfilled = true;
+            // Mark the trigger as fulfilled by the minted token
+            receipt.fulfilled = true;
+            receipt.tokenId = fulfillment.tokenId;
+            receipt.tokenURI = fulfillment.tokenURI;
 
-        // Emit the fulfillment event
-        emit MintFulfilled(triggerId);
+            // Emit the fulfillment event
+            emit MintFulfilled(triggerId);
+        } else if (
+            fulfillment.kind == IWavsNftServiceTypes.WavsTriggerType.UPDATE
+        ) {
+            // Updates are triggered on the NFT contract, so there is no receipt to check
+            UpdateReceipt storage updateReceipt = updateReceipts[triggerId];
+            require(!updateReceipt.fulfilled, "Trigger already fulfilled");
+
+            updateReceipt.fulfilled = true;
+            updateReceipt.tokenId = fulfillment.tokenId;
+            updateReceipt.tokenURI = fulfillment.tokenURI;
+
+            emit UpdateFulfilled(
+                triggerId,
+                fulfillment.tokenId,
+                fulfillment.tokenURI
+            );
+        } else {
+            revert("Unsupported fulfillment kind");
+        }
     }
 
     /**
@@ -142,6 +304,24 @@ contract WavsMinter is Ownable, ReentrancyGuard, IWavsServiceHandler {
         emit MintPriceUpdated(newPrice);
     }
 
+    /**
+     * @notice Set the NFT contract used for reveal mints (owner only)
+     * @param newNft The WavsNft contract, which must grant this contract the MINTER_ROLE
+     */
+    function setNft(address newNft) external onlyOwner {
+        nft = WavsNft(newNft);
+        emit NftUpdated(newNft);
+    }
+
+    /**
+     * @notice Set the chain the NFT contract is on, when it differs from this one (owner only)
+     * @param newChainId The chain ID fulfillments must come from, 0 to accept any
+     */
+    function setNftChainId(uint256 newChainId) external onlyOwner {
+        nftChainId = newChainId;
+        emit NftChainIdUpdated(newChainId);
+    }
+
     /**
      * @notice Withdraw collected fees (owner only)
      */
@@ -165,4 +345,15 @@ contract WavsMinter is Ownable, ReentrancyGuard, IWavsServiceHandler {
     ) external view returns (Receipt memory) {
         return receipts[triggerId];
     }
+
+    /**
+     * @notice Get the fulfillment of an update
+     * @param triggerId The update trigger ID, as assigned by the NFT contract
+     * @return The update receipt struct
+     */
+    function getUpdateReceipt(
+        IWavsNftServiceTypes.TriggerId triggerId
+    ) external view returns (UpdateReceipt memory) {
+        return updateReceipts[triggerId];
+    }
 }
@@ -34,6 +34,12 @@ contract WavsNft is
     uint256 public updateFee = 0.01 ether;
     address public fundsRecipient;
 
+    // Token URI of minted NFTs until the AVS reveals their art
+    string public placeholderURI;
+
+    // Tokens minted with the placeholder URI that have not been revealed yet
+    mapping(uint256 => bool) public pendingReveal;
+
     constructor(
         address serviceManager_,
         address fundsRecipient_
@@ -87,21 +93,45 @@ contract WavsNft is
                 (IWavsNftServiceTypes.WavsMintResult)
             );
 
-            // Increment the tokenId
-            uint256 tokenId = nextTokenId++;
+            _mintResult(mintResult);
+        } else if (
+            wavsResponse.wavsTriggerType ==
+            IWavsNftServiceTypes.WavsTriggerType.BATCH_MINT
+        ) {
+            // Decode the mint info of every token in the batch
+            IWavsNftServiceTypes.WavsMintResult[] memory mintResults = abi
+                .decode(
+                    wavsResponse.data,
+                    (IWavsNftServiceTypes.WavsMintResult[])
+                );
 
-            // Mint the NFT
-            _safeMint(mintResult.recipient, tokenId);
+            for (uint256 i = 0; i < mintResults.length; i++) {
+                _mintResult(mintResults[i]);
+            }
+        } else if (
+            wavsResponse.wavsTriggerType ==
+            IWavsNftServiceTypes.WavsTriggerType.REVEAL
+        ) {
+            // Decode the reveal info
+            IWavsNftServiceTypes.WavsUpdateResult memory revealResult = abi
+                .decode(
+                    wavsResponse.data,
+                    (IWavsNftServiceTypes.WavsUpdateResult)
+                );
+            require(
+                pendingReveal[revealResult.tokenId],
+                "Token is not pending reveal"
+            );
+            pendingReveal[revealResult.tokenId] = false;
 
-            // Set the tokenURI
-            _setTokenURI(tokenId, mintResult.tokenURI);
+            // Replace the placeholder with the generated art
+            _setTokenURI(revealResult.tokenId, revealResult.tokenURI);
 
-            // Emit event to notify the minter contract that the mint has been fulfilled
-            emit IWavsNftServiceTypes.WavsNftMint(
-                mintResult.recipient,
-                tokenId,
-                mintResult.tokenURI,
-                IWavsNftServiceTypes.TriggerId.unwrap(mintResult.triggerId)
+            emit IWavsNftServiceTypes.WavsNftReveal(
+                ownerOf(revealResult.tokenId),
+                revealResult.tokenId,
+                revealResult.tokenURI,
+                IWavsNftServiceTypes.TriggerId.unwrap(revealResult.triggerId)
             );
         } else if (
             wavsResponse.wavsTriggerType ==
@@ -127,6 +157,27 @@ contract WavsNft is
         }
     }
 
+    function _mintResult(
+        IWavsNftServiceTypes.WavsMintResult memory mintResult
+    ) internal {
+        // Increment the tokenId
+        uint256 tokenId = nextTokenId++;
+
+        // Mint the NFT
+        _safeMint(mintResult.recipient, tokenId);
+
+        // Set the tokenURI
+        _setTokenURI(tokenId, mintResult.tokenURI);
+
+        // Emit event to notify the minter contract that the mint has been fulfilled
+        emit IWavsNftServiceTypes.WavsNftMint(
+            mintResult.recipient,
+            tokenId,
+            mintResult.tokenURI,
+            IWavsNftServiceTypes.TriggerId.unwrap(mintResult.triggerId)
+        );
+    }
+
     // Add tokenURI override
     function tokenURI(
         uint256 tokenId
@@ -200,6 +251,49 @@ contract WavsNft is
         fundsRecipient = newRecipient;
     }
 
+    /**
+     * @notice Sets the token URI of NFTs awaiting their reveal
+     * @param newPlaceholderURI The new placeholder token URI
+     */
+    function setPlaceholderURI(
+        string calldata newPlaceholderURI
+    ) external onlyRole(DEFAULT_ADMIN_ROLE) {
+        placeholderURI = newPlaceholderURI;
+    }
+
+    /**
+     * @notice Mints an NFT with the placeholder URI and triggers the AVS to generate and reveal its art
+     * @param to The recipient of the NFT
+     * @param prompt The text prompt for AI generation
+     * @param options ABI-encoded IWavsNftServiceTypes.WavsMintOptions, or empty for the defaults
+     * @return tokenId The ID of the minted NFT
+     */
+    function mintPlaceholder(
+        address to,
+        string calldata prompt,
+        bytes calldata options
+    ) external onlyRole(MINTER_ROLE) returns (uint256 tokenId) {
+        tokenId = nextTokenId++;
+        _safeMint(to, tokenId);
+        _setTokenURI(tokenId, placeholderURI);
+        pendingReveal[tokenId] = true;
+
+        // Increment trigger ID
+        nextTriggerId = IWavsNftServiceTypes.TriggerId.wrap(
+            IWavsNftServiceTypes.TriggerId.unwrap(nextTriggerId) + 1
+        );
+
+        // Emit trigger event
+        emit IWavsNftServiceTypes.WavsNftTrigger(
+            to,
+            prompt,
+            IWavsNftServiceTypes.TriggerId.unwrap(nextTriggerId),
+            uint8(IWavsNftServiceTypes.WavsTriggerType.REVEAL),
+            tokenId,
+            options
+        );
+    }
+
     /**
      * @notice Triggers an update for an existing NFT
      * @param tokenId The ID of the NFT to update
@@ -209,8 +303,31 @@ contract WavsNft is
         uint256 tokenId,
         string calldata prompt
     ) external payable {
+        _triggerUpdate(tokenId, prompt, "");
+    }
+
+    /**
+     * @notice Triggers an update for an existing NFT with mint-time parameters
+     * @param tokenId The ID of the NFT to update
+     * @param prompt The text prompt for AI generation
+     * @param options ABI-encoded IWavsNftServiceTypes.WavsMintOptions
+     */
+    function triggerUpdateWithOptions(
+        uint256 tokenId,
+        string calldata prompt,
+        bytes calldata options
+    ) external payable {
+        _triggerUpdate(tokenId, prompt, options);
+    }
+
+    function _triggerUpdate(
+        uint256 tokenId,
+        string calldata prompt,
+        bytes memory options
+    ) internal {
         require(msg.value >= updateFee, "Insufficient update fee");
         require(ownerOf(tokenId) == msg.sender, "Not NFT owner");
+        require(!pendingReveal[tokenId], "Token is pending reveal");
 
         // Refund any excess payment
         uint256 excess = msg.value - updateFee;
@@ -232,7 +349,8 @@ contract WavsNft is
             prompt,
             IWavsNftServiceTypes.TriggerId.unwrap(nextTriggerId),
             uint8(IWavsNftServiceTypes.WavsTriggerType.UPDATE),
-            tokenId
+            tokenId,
+            options
         );
     }
 }
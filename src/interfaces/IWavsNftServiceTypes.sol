@@ -16,14 +16,53 @@ interface IWavsNftServiceTypes {
      */
     enum WavsTriggerType {
         MINT,
-        UPDATE
+        UPDATE,
+        BATCH_MINT,
+        REVEAL
+    }
+
+    /**
+     * @notice Aspect ratio of the generated image
+     */
+    enum WavsAspectRatio {
+        SQUARE,
+        PORTRAIT,
+        LANDSCAPE
+    }
+
+    /**
+     * @notice Optional mint-time parameters, ABI-encoded into the `options` of a trigger
+     * @dev Values are validated against an allow-list by the AVS, an empty `options` uses the defaults
+     * @param stylePresetId The ID of the style preset, 0 for the collection default
+     * @param aspectRatio The aspect ratio of the generated image
+     * @param negativePrompt Additions to the negative prompt
+     * @param palette The name of the color palette, empty for no palette
+     */
+    struct WavsMintOptions {
+        uint16 stylePresetId;
+        WavsAspectRatio aspectRatio;
+        string negativePrompt;
+        string palette;
+    }
+
+    /**
+     * @notice Parameters of a BATCH_MINT trigger, ABI-encoded into the `options` of the trigger
+     * @dev Every prompt is minted `variants` times, each variant with its own derived seeds
+     * @param prompts The prompts to mint, or empty to use the prompt of the trigger
+     * @param variants The number of variants of each prompt, at least 1
+     * @param options ABI-encoded WavsMintOptions applied to every token, or empty for the defaults
+     */
+    struct WavsBatchMintOptions {
+        string[] prompts;
+        uint16 variants;
+        bytes options;
     }
 
     /**
      * @notice Struct to store the WAVS response data
      * @param triggerId The trigger ID
      * @param wavsTriggerType The type of trigger
-     * @param data Contains WavsMintResult or WavsUpdateResult
+     * @param data Contains WavsMintResult, WavsUpdateResult or WavsMintResult[] for BATCH_MINT. REVEAL uses WavsUpdateResult
      */
     struct WavsResponse {
         TriggerId triggerId;
@@ -38,13 +77,15 @@ interface IWavsNftServiceTypes {
      * @param triggerId The ID of the trigger
      * @param wavsTriggerType The type of trigger
      * @param tokenId The ID of the NFT, for new mints, this is ignored by the AVS
+     * @param options ABI-encoded WavsMintOptions, or empty for the defaults. WavsBatchMintOptions for BATCH_MINT
      */
     event WavsNftTrigger(
         address indexed sender,
         string prompt,
         uint64 indexed triggerId,
         uint8 wavsTriggerType,
-        uint256 tokenId
+        uint256 tokenId,
+        bytes options
     );
 
     /**
@@ -75,12 +116,38 @@ interface IWavsNftServiceTypes {
         uint64 triggerId
     );
 
+    /**
+     * @notice Event emitted when the art of a placeholder NFT is revealed via the AVS
+     * @param owner The owner of the NFT that has been revealed
+     * @param tokenId The ID of the revealed NFT
+     * @param tokenURI The URI of the NFT data
+     * @param triggerId The ID of the trigger that initiated the reveal
+     */
+    event WavsNftReveal(
+        address indexed owner,
+        uint256 indexed tokenId,
+        string tokenURI,
+        uint64 triggerId
+    );
+
     /**
      * @notice Event emitted when a mint is fulfilled
      * @param triggerId The ID of the fulfilled trigger
      */
     event MintFulfilled(TriggerId indexed triggerId);
 
+    /**
+     * @notice Event emitted when an update is fulfilled
+     * @param triggerId The ID of the fulfilled update trigger, numbered by the NFT contract
+     * @param tokenId The ID of the updated NFT
+     * @param tokenURI The URI of the NFT data
+     */
+    event UpdateFulfilled(
+        TriggerId indexed triggerId,
+        uint256 indexed tokenId,
+        string tokenURI
+    );
+
     /**
      * @notice Struct to store the result of a mint operation
      * @param triggerId The trigger ID
@@ -105,4 +172,50 @@ interface IWavsNftServiceTypes {
         string tokenURI;
         uint256 tokenId;
     }
+
+    /**
+     * @notice Struct relayed to the minter once a trigger has been carried out
+     * @param triggerId The ID of the fulfilled trigger
+     * @param kind MINT for a WavsNftMint, including the tokens of a BATCH_MINT, or UPDATE for a WavsNftUpdate
+     * @param tokenId The ID of the NFT that satisfied the trigger
+     * @param tokenURI The URI of the NFT data
+     * @param origin Where the relayed event was emitted, which may be another chain than the minter's
+     */
+    struct WavsFulfillment {
+        TriggerId triggerId;
+        WavsTriggerType kind;
+        uint256 tokenId;
+        string tokenURI;
+        WavsLogOrigin origin;
+    }
+
+    /**
+     * @notice Coordinates of a relayed event, identifying it across chains
+     * @param chainId The ID of the chain the event was emitted on
+     * @param blockHash The hash of the block containing the event
+     * @param transactionHash The hash of the transaction that emitted the event
+     * @param logIndex The index of the event in its block
+     */
+    struct WavsLogOrigin {
+        uint256 chainId;
+        bytes32 blockHash;
+        bytes32 transactionHash;
+        uint256 logIndex;
+    }
+
+    /**
+     * @notice A WavsNftTrigger event log replayed through the raw trigger path, e.g. for a trigger no operator carried out
+     * @param chainName The WAVS name of the chain the event was emitted on
+     * @param emitter The contract that emitted the event
+     * @param blockNumber The block the event was emitted in
+     * @param topics The topics of the event log
+     * @param data The data of the event log
+     */
+    struct WavsRawTrigger {
+        string chainName;
+        address emitter;
+        uint64 blockNumber;
+        bytes32[] topics;
+        bytes data;
+    }
 }
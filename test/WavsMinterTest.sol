@@ -73,6 +73,121 @@ contract WavsMinterTest is Test {
         vm.stopPrank();
     }
 
+    function testTriggerMintWithOptions() public {
+        vm.deal(user1, 1 ether);
+        vm.startPrank(user1);
+
+        string memory prompt = "Test prompt for NFT";
+        bytes memory options = abi.encode(
+            IWavsNftServiceTypes.WavsMintOptions({
+                stylePresetId: 1,
+                aspectRatio: IWavsNftServiceTypes.WavsAspectRatio.PORTRAIT,
+                negativePrompt: "text, watermark",
+                palette: "pastel"
+            })
+        );
+
+        vm.expectEmit(true, true, false, true, address(minter));
+        emit WavsMinter.WavsNftTrigger(
+            user1,
+            prompt,
+            0,
+            uint8(IWavsNftServiceTypes.WavsTriggerType.MINT),
+            0,
+            options
+        );
+
+        IWavsNftServiceTypes.TriggerId triggerId = minter
+            .triggerMintWithOptions{value: mintPrice}(prompt, options);
+
+        WavsMinter.Receipt memory receipt = minter.getTrigger(triggerId);
+        assertEq(receipt.creator, user1);
+        assertEq(receipt.prompt, prompt);
+        assertFalse(receipt.fulfilled);
+
+        vm.stopPrank();
+    }
+
+    function testTriggerBatchMint() public {
+        vm.deal(user1, 1 ether);
+        vm.startPrank(user1);
+
+        string[] memory prompts = new string[](2);
+        prompts[0] = "First prompt";
+        prompts[1] = "Second prompt";
+        bytes memory batchOptions = abi.encode(
+            IWavsNftServiceTypes.WavsBatchMintOptions({
+                prompts: prompts,
+                variants: 2,
+                options: ""
+            })
+        );
+
+        vm.expectEmit(true, true, false, true, address(minter));
+        emit WavsMinter.WavsNftTrigger(
+            user1,
+            "",
+            0,
+            uint8(IWavsNftServiceTypes.WavsTriggerType.BATCH_MINT),
+            0,
+            batchOptions
+        );
+
+        uint256 initialBalance = user1.balance;
+        IWavsNftServiceTypes.TriggerId triggerId = minter.triggerBatchMint{
+            value: 0.5 ether
+        }("", batchOptions);
+
+        // Four tokens are paid for and the excess is refunded
+        assertEq(user1.balance, initialBalance - mintPrice * 4);
+
+        WavsMinter.Receipt memory receipt = minter.getTrigger(triggerId);
+        assertEq(receipt.creator, user1);
+        assertEq(
+            uint8(receipt.wavsTriggerType),
+            uint8(IWavsNftServiceTypes.WavsTriggerType.BATCH_MINT)
+        );
+        assertFalse(receipt.fulfilled);
+
+        vm.stopPrank();
+    }
+
+    function test_RevertWhen_BatchMintIsTooLarge() public {
+        vm.deal(user1, 10 ether);
+        vm.startPrank(user1);
+
+        bytes memory batchOptions = abi.encode(
+            IWavsNftServiceTypes.WavsBatchMintOptions({
+                prompts: new string[](0),
+                variants: 11,
+                options: ""
+            })
+        );
+
+        vm.expectRevert("Batch too large");
+        minter.triggerBatchMint{value: 2 ether}("Test prompt", batchOptions);
+
+        vm.stopPrank();
+    }
+
+    function test_RevertWhen_BatchMintWithInsufficientPayment() public {
+        vm.deal(user1, 1 ether);
+        vm.startPrank(user1);
+
+        bytes memory batchOptions = abi.encode(
+            IWavsNftServiceTypes.WavsBatchMintOptions({
+                prompts: new string[](0),
+                variants: 3,
+                options: ""
+            })
+        );
+
+        vm.expectRevert("Insufficient payment");
+        minter.triggerBatchMint{value: 0.2 ether}("Test prompt", batchOptions);
+
+        vm.stopPrank();
+    }
+
     function testTriggerMintWithExcessPayment() public {
         vm.deal(user1, 1 ether);
         vm.startPrank(user1);
@@ -143,8 +258,16 @@ contract WavsMinterTest is Test {
             value: mintPrice
         }(prompt);
 
-        // Create and encode the response with the triggerId
-        bytes memory data = abi.encode(triggerId);
+        // Create and encode the fulfillment of the triggerId
+        bytes memory data = abi.encode(
+            IWavsNftServiceTypes.WavsFulfillment({
+                triggerId: triggerId,
+                kind: IWavsNftServiceTypes.WavsTriggerType.MINT,
+                tokenId: 0,
+                tokenURI: "ipfs://some-token-uri",
+                origin: _origin(0)
+            })
+        );
         bytes memory signature = ""; // Mock signature
 
         // First emit the expected event to record its pattern
@@ -155,9 +278,108 @@ contract WavsMinterTest is Test {
         vm.prank(address(0)); // Can be any address since we're using a mock
         minter.handleSignedData(data, signature);
 
-        // Check that the receipt was marked as fulfilled
+        // Check that the receipt was marked as fulfilled by the token
         WavsMinter.Receipt memory receipt = minter.getTrigger(triggerId);
         assertTrue(receipt.fulfilled);
+        assertEq(receipt.tokenId, 0);
+        assertEq(receipt.tokenURI, "ipfs://some-token-uri");
+    }
+
+    function testHandleSignedDataForUpdate() public {
+        // Update trigger IDs are assigned by the NFT contract, no receipt exists here
+        IWavsNftServiceTypes.TriggerId triggerId = IWavsNftServiceTypes
+            .TriggerId
+            .wrap(7);
+        bytes memory data = abi.encode(
+            IWavsNftServiceTypes.WavsFulfillment({
+                triggerId: triggerId,
+                kind: IWavsNftServiceTypes.WavsTriggerType.UPDATE,
+                tokenId: 3,
+                tokenURI: "ipfs://updated-uri",
+                origin: _origin(0)
+            })
+        );
+
+        vm.expectEmit(true, true, false, true, address(minter));
+        emit IWavsNftServiceTypes.UpdateFulfilled(
+            triggerId,
+            3,
+            "ipfs://updated-uri"
+        );
+
+        vm.prank(address(0));
+        minter.handleSignedData(data, "");
+
+        WavsMinter.UpdateReceipt memory updateReceipt = minter
+            .getUpdateReceipt(triggerId);
+        assertTrue(updateReceipt.fulfilled);
+        assertEq(updateReceipt.tokenId, 3);
+        assertEq(updateReceipt.tokenURI, "ipfs://updated-uri");
+
+        // The mint receipt with the same ID is left untouched
+        assertFalse(minter.getTrigger(triggerId).fulfilled);
+
+        // Relaying the same event again fails
+        vm.expectRevert("Event already relayed");
+        vm.prank(address(0));
+        minter.handleSignedData(data, "");
+    }
+
+    function test_RevertWhen_RelayingFromAnotherChain() public {
+        vm.prank(owner);
+        minter.setNftChainId(block.chainid + 1);
+
+        vm.deal(user1, 1 ether);
+        vm.prank(user1);
+        IWavsNftServiceTypes.TriggerId triggerId = minter.triggerMint{
+            value: mintPrice
+        }("Test prompt for NFT");
+
+        bytes memory data = abi.encode(
+            IWavsNftServiceTypes.WavsFulfillment({
+                triggerId: triggerId,
+                kind: IWavsNftServiceTypes.WavsTriggerType.MINT,
+                tokenId: 0,
+                tokenURI: "ipfs://some-token-uri",
+                origin: _origin(0)
+            })
+        );
+
+        vm.expectRevert("Event from another chain");
+        vm.prank(address(0));
+        minter.handleSignedData(data, "");
+    }
+
+    function testSetNftChainId() public {
+        vm.expectEmit(false, false, false, true, address(minter));
+        emit WavsMinter.NftChainIdUpdated(10);
+
+        vm.prank(owner);
+        minter.setNftChainId(10);
+        assertEq(minter.nftChainId(), 10);
+    }
+
+    function test_RevertWhen_NonOwnerSetsNftChainId() public {
+        // Should revert when called by non-owner
+        vm.expectRevert();
+        vm.prank(user1);
+        minter.setNftChainId(10);
+    }
+
+    function test_RevertWhen_HandlingUnsupportedFulfillmentKind() public {
+        bytes memory data = abi.encode(
+            IWavsNftServiceTypes.WavsFulfillment({
+                triggerId: minter.nextTriggerId(),
+                kind: IWavsNftServiceTypes.WavsTriggerType.REVEAL,
+                tokenId: 0,
+                tokenURI: "ipfs://some-token-uri",
+                origin: _origin(0)
+            })
+        );
+
+        vm.expectRevert("Unsupported fulfillment kind");
+        vm.prank(address(0));
+        minter.handleSignedData(data, "");
     }
 
     function test_RevertWhen_HandlingNonExistentTrigger() public {
@@ -167,7 +389,15 @@ contract WavsMinterTest is Test {
                 .wrap(999);
 
         // Encode the response
-        bytes memory data = abi.encode(nonExistentTriggerId);
+        bytes memory data = abi.encode(
+            IWavsNftServiceTypes.WavsFulfillment({
+                triggerId: nonExistentTriggerId,
+                kind: IWavsNftServiceTypes.WavsTriggerType.MINT,
+                tokenId: 0,
+                tokenURI: "ipfs://some-token-uri",
+                origin: _origin(0)
+            })
+        );
         bytes memory signature = ""; // Mock signature
 
         // This should revert because the trigger doesn't exist
@@ -186,12 +416,29 @@ contract WavsMinterTest is Test {
         }(prompt);
 
         // Mark it as fulfilled
-        bytes memory data = abi.encode(triggerId);
+        bytes memory data = abi.encode(
+            IWavsNftServiceTypes.WavsFulfillment({
+                triggerId: triggerId,
+                kind: IWavsNftServiceTypes.WavsTriggerType.MINT,
+                tokenId: 0,
+                tokenURI: "ipfs://some-token-uri",
+                origin: _origin(0)
+            })
+        );
         bytes memory signature = ""; // Mock signature
         vm.prank(address(0));
         minter.handleSignedData(data, signature);
 
-        // Try to fulfill it again - this should fail
+        // Try to fulfill it again from another event - this should fail
+        data = abi.encode(
+            IWavsNftServiceTypes.WavsFulfillment({
+                triggerId: triggerId,
+                kind: IWavsNftServiceTypes.WavsTriggerType.MINT,
+                tokenId: 1,
+                tokenURI: "ipfs://another-token-uri",
+                origin: _origin(1)
+            })
+        );
         vm.expectRevert("Trigger already fulfilled");
         vm.prank(address(0));
         minter.handleSignedData(data, signature);
@@ -255,4 +502,17 @@ contract WavsMinterTest is Test {
         vm.prank(owner);
         minter.withdrawFees();
     }
+
+    // Coordinates of a relayed event, distinct for every log index
+    function _origin(
+        uint256 logIndex
+    ) internal view returns (IWavsNftServiceTypes.WavsLogOrigin memory) {
+        return
+            IWavsNftServiceTypes.WavsLogOrigin({
+                chainId: block.chainid,
+                blockHash: keccak256("block"),
+                transactionHash: keccak256("transaction"),
+                logIndex: logIndex
+            });
+    }
 }
@@ -129,6 +129,43 @@ contract WavsNftDetailedTest is Test {
         assertEq(nft.nextTokenId(), 1);
     }
 
+    function testHandleSignedDataForBatchMint() public {
+        IWavsNftServiceTypes.TriggerId triggerId = IWavsNftServiceTypes
+            .TriggerId
+            .wrap(1);
+        IWavsNftServiceTypes.WavsMintResult[]
+            memory mintResults = new IWavsNftServiceTypes.WavsMintResult[](2);
+        mintResults[0] = IWavsNftServiceTypes.WavsMintResult({
+            triggerId: triggerId,
+            recipient: user1,
+            tokenURI: "ipfs://batch-token-uri-0"
+        });
+        mintResults[1] = IWavsNftServiceTypes.WavsMintResult({
+            triggerId: triggerId,
+            recipient: user1,
+            tokenURI: "ipfs://batch-token-uri-1"
+        });
+
+        IWavsNftServiceTypes.WavsResponse
+            memory wavsResponse = IWavsNftServiceTypes.WavsResponse({
+                triggerId: triggerId,
+                wavsTriggerType: IWavsNftServiceTypes
+                    .WavsTriggerType
+                    .BATCH_MINT,
+                data: abi.encode(mintResults)
+            });
+
+        vm.prank(address(0));
+        nft.handleSignedData(abi.encode(wavsResponse), "");
+
+        // Check that every NFT in the batch was minted
+        assertEq(nft.ownerOf(0), user1);
+        assertEq(nft.tokenURI(0), "ipfs://batch-token-uri-0");
+        assertEq(nft.ownerOf(1), user1);
+        assertEq(nft.tokenURI(1), "ipfs://batch-token-uri-1");
+        assertEq(nft.nextTokenId(), 2);
+    }
+
     function testHandleSignedDataForUpdate() public {
         // First mint an NFT
         IWavsNftServiceTypes.TriggerId mintTriggerId = IWavsNftServiceTypes
@@ -218,7 +255,8 @@ contract WavsNftDetailedTest is Test {
             updatePrompt,
             1, // This will be the TriggerId since we're starting from 0
             uint8(IWavsNftServiceTypes.WavsTriggerType.UPDATE),
-            0 // tokenId
+            0, // tokenId
+            "" // options
         );
 
         nft.triggerUpdate{value: updateFee}(0, updatePrompt);
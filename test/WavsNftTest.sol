@@ -139,7 +139,15 @@ contract WavsNftTest is Test {
         assertEq(nft.tokenURI(0), "ipfs://some-token-uri");
 
         // Now call handleSignedData on the minter contract to complete the process
-        data = abi.encode(triggerId);
+        data = abi.encode(
+            IWavsNftServiceTypes.WavsFulfillment({
+                triggerId: triggerId,
+                kind: IWavsNftServiceTypes.WavsTriggerType.MINT,
+                tokenId: 0,
+                tokenURI: "ipfs://some-token-uri",
+                origin: _origin(0)
+            })
+        );
         vm.prank(address(0));
         minter.handleSignedData(data, signature);
 
@@ -216,6 +224,83 @@ contract WavsNftTest is Test {
         assertEq(nft.tokenURI(0), "ipfs://updated-token-uri");
     }
 
+    function testRevealMint() public {
+        // Enable reveal mints
+        vm.startPrank(owner);
+        minter.setNft(address(nft));
+        nft.setPlaceholderURI("ipfs://placeholder-uri");
+        vm.stopPrank();
+
+        // Mint a placeholder
+        vm.deal(user1, 1 ether);
+        vm.prank(user1);
+        string memory prompt = "Test prompt for NFT";
+        uint256 tokenId = minter.triggerRevealMint{value: mintPrice}(
+            prompt,
+            ""
+        );
+
+        // The NFT is minted immediately with the placeholder
+        assertEq(nft.ownerOf(tokenId), user1);
+        assertEq(nft.tokenURI(tokenId), "ipfs://placeholder-uri");
+        assertTrue(nft.pendingReveal(tokenId));
+
+        // Simulate the reveal being completed
+        IWavsNftServiceTypes.TriggerId revealTriggerId = nft.nextTriggerId();
+        IWavsNftServiceTypes.WavsUpdateResult
+            memory revealResult = IWavsNftServiceTypes.WavsUpdateResult({
+                triggerId: revealTriggerId,
+                owner: user1,
+                tokenURI: "ipfs://revealed-token-uri",
+                tokenId: tokenId
+            });
+
+        IWavsNftServiceTypes.WavsResponse
+            memory revealResponse = IWavsNftServiceTypes.WavsResponse({
+                triggerId: revealTriggerId,
+                wavsTriggerType: IWavsNftServiceTypes.WavsTriggerType.REVEAL,
+                data: abi.encode(revealResult)
+            });
+
+        bytes memory data = abi.encode(revealResponse);
+        bytes memory signature = ""; // Mock signature
+
+        vm.prank(address(0));
+        nft.handleSignedData(data, signature);
+
+        // Check that the art was revealed
+        assertEq(nft.tokenURI(tokenId), "ipfs://revealed-token-uri");
+        assertFalse(nft.pendingReveal(tokenId));
+
+        // A token can only be revealed once
+        vm.expectRevert("Token is not pending reveal");
+        vm.prank(address(0));
+        nft.handleSignedData(data, signature);
+    }
+
+    function test_RevertWhen_RevealMintingWithoutNft() public {
+        vm.deal(user1, 1 ether);
+        vm.prank(user1);
+        vm.expectRevert("Reveal mints are not enabled");
+        minter.triggerRevealMint{value: mintPrice}("Test prompt for NFT", "");
+    }
+
+    function test_RevertWhen_UpdatingPendingReveal() public {
+        vm.prank(owner);
+        minter.setNft(address(nft));
+
+        vm.deal(user1, 1 ether);
+        vm.prank(user1);
+        uint256 tokenId = minter.triggerRevealMint{value: mintPrice}(
+            "Test prompt for NFT",
+            ""
+        );
+
+        vm.prank(user1);
+        vm.expectRevert("Token is pending reveal");
+        nft.triggerUpdate{value: updateFee}(tokenId, "Updated prompt");
+    }
+
     function testAdminFunctions() public {
         // Test setting mint price
         vm.prank(owner);
@@ -248,4 +333,17 @@ contract WavsNftTest is Test {
         assertEq(owner.balance, initialOwnerBalance + 1 ether);
         assertEq(address(minter).balance, 0);
     }
+
+    // Coordinates of a relayed event, distinct for every log index
+    function _origin(
+        uint256 logIndex
+    ) internal view returns (IWavsNftServiceTypes.WavsLogOrigin memory) {
+        return
+            IWavsNftServiceTypes.WavsLogOrigin({
+                chainId: block.chainid,
+                blockHash: keccak256("block"),
+                transactionHash: keccak256("transaction"),
+                logIndex: logIndex
+            });
+    }
 }
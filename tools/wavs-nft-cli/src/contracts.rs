@@ -0,0 +1,210 @@
+use alloy_network::TransactionBuilder;
+use alloy_primitives::{Address, B256, U256};
+use alloy_provider::Provider;
+use alloy_rpc_types::{Log, TransactionReceipt, TransactionRequest};
+use alloy_sol_macro::sol;
+use alloy_sol_types::{SolCall, SolEvent};
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Value};
+
+sol!("../../src/interfaces/IWavsNftServiceTypes.sol");
+
+sol! {
+    interface IWavsMinter {
+        struct Receipt {
+            address creator;
+            string prompt;
+            uint8 wavsTriggerType;
+            bool fulfilled;
+            uint256 tokenId;
+            string tokenURI;
+        }
+
+        struct UpdateReceipt {
+            uint256 tokenId;
+            string tokenURI;
+            bool fulfilled;
+        }
+
+        function mintPrice() external view returns (uint256);
+        function triggerMint(string prompt) external payable returns (uint64);
+        function triggerMintWithOptions(string prompt, bytes options) external payable returns (uint64);
+        function getTrigger(uint64 triggerId) external view returns (Receipt memory);
+        function getUpdateReceipt(uint64 triggerId) external view returns (UpdateReceipt memory);
+    }
+
+    interface IWavsNft {
+        function updateFee() external view returns (uint256);
+        function triggerUpdate(uint256 tokenId, string prompt) external payable;
+        function triggerUpdateWithOptions(uint256 tokenId, string prompt, bytes options) external payable;
+        function ownerOf(uint256 tokenId) external view returns (address);
+        function tokenURI(uint256 tokenId) external view returns (string);
+        function totalSupply() external view returns (uint256);
+        function tokenByIndex(uint256 index) external view returns (uint256);
+    }
+}
+
+use IWavsNftServiceTypes::{
+    MintFulfilled, UpdateFulfilled, WavsNftMint, WavsNftReveal, WavsNftTrigger, WavsNftUpdate,
+};
+
+/// IDs of every existing token of the NFT contract, enumerating skips burned tokens
+pub async fn token_ids<P: Provider>(provider: &P, nft: Address) -> Result<Vec<U256>> {
+    let supply = call(provider, nft, IWavsNft::totalSupplyCall {}).await?._0;
+    let mut token_ids = Vec::new();
+    let mut index = U256::ZERO;
+    while index < supply {
+        token_ids.push(call(provider, nft, IWavsNft::tokenByIndexCall { index }).await?._0);
+        index += U256::from(1);
+    }
+    Ok(token_ids)
+}
+
+/// Make a read-only call and decode its return values
+pub async fn call<P: Provider, C: SolCall>(
+    provider: &P,
+    to: Address,
+    call: C,
+) -> Result<C::Return> {
+    let tx = TransactionRequest::default().with_to(to).with_input(call.abi_encode());
+    let result = provider.call(&tx).await.with_context(|| format!("{} failed", C::SIGNATURE))?;
+    C::abi_decode_returns(&result, true)
+        .with_context(|| format!("Failed to decode the result of {}", C::SIGNATURE))
+}
+
+/// Send a transaction and wait for it to be mined, failing if it reverted
+pub async fn send<P: Provider, C: SolCall>(
+    provider: &P,
+    to: Address,
+    call: C,
+    value: U256,
+) -> Result<TransactionReceipt> {
+    let tx =
+        TransactionRequest::default().with_to(to).with_input(call.abi_encode()).with_value(value);
+    let receipt = provider
+        .send_transaction(tx)
+        .await
+        .with_context(|| format!("{} failed", C::SIGNATURE))?
+        .get_receipt()
+        .await?;
+    if !receipt.status() {
+        bail!("Transaction {} reverted", receipt.transaction_hash);
+    }
+    Ok(receipt)
+}
+
+/// The `WavsNftTrigger` event emitted by a mint or update transaction
+pub fn trigger_event(receipt: &TransactionReceipt) -> Result<WavsNftTrigger> {
+    let log = receipt
+        .inner
+        .logs()
+        .iter()
+        .find(|log| log.topic0() == Some(&WavsNftTrigger::SIGNATURE_HASH))
+        .ok_or_else(|| anyhow!("No WavsNftTrigger event in {}", receipt.transaction_hash))?;
+    Ok(log.log_decode::<WavsNftTrigger>()?.inner.data)
+}
+
+/// The token URI set by the NFT contract log at `log_index` of a transaction
+pub async fn token_uri_set_by<P: Provider>(
+    provider: &P,
+    nft: Address,
+    transaction_hash: B256,
+    log_index: u64,
+) -> Result<String> {
+    let receipt = provider
+        .get_transaction_receipt(transaction_hash)
+        .await?
+        .ok_or_else(|| anyhow!("Transaction {} not found", transaction_hash))?;
+    let log = receipt
+        .inner
+        .logs()
+        .iter()
+        .find(|log| log.log_index == Some(log_index) && log.address() == nft)
+        .ok_or_else(|| anyhow!("No NFT log {} in {}", log_index, transaction_hash))?;
+    let signature = log.topic0().copied().unwrap_or_default();
+    let token_uri = if signature == WavsNftMint::SIGNATURE_HASH {
+        log.log_decode::<WavsNftMint>()?.inner.data.tokenURI
+    } else if signature == WavsNftUpdate::SIGNATURE_HASH {
+        log.log_decode::<WavsNftUpdate>()?.inner.data.tokenURI
+    } else if signature == WavsNftReveal::SIGNATURE_HASH {
+        log.log_decode::<WavsNftReveal>()?.inner.data.tokenURI
+    } else {
+        bail!("Log {} of {} does not set a token URI", log_index, transaction_hash);
+    };
+    Ok(token_uri)
+}
+
+pub fn trigger_type_name(wavs_trigger_type: u8) -> &'static str {
+    match wavs_trigger_type {
+        0 => "MINT",
+        1 => "UPDATE",
+        2 => "BATCH_MINT",
+        3 => "REVEAL",
+        _ => "UNKNOWN",
+    }
+}
+
+/// Describe a log of the minter or NFT contract, `None` for events the CLI does not follow
+pub fn describe_log(log: &Log) -> Option<Value> {
+    let signature = *log.topic0()?;
+    let (event, fields) = if signature == WavsNftTrigger::SIGNATURE_HASH {
+        let event = log.log_decode::<WavsNftTrigger>().ok()?.inner.data;
+        let fields = json!({
+            "sender": event.sender.to_string(),
+            "prompt": event.prompt,
+            "triggerId": event.triggerId,
+            "wavsTriggerType": trigger_type_name(event.wavsTriggerType),
+            "tokenId": event.tokenId.to_string(),
+            "options": event.options.to_string(),
+        });
+        (WavsNftTrigger::SIGNATURE, fields)
+    } else if signature == WavsNftMint::SIGNATURE_HASH {
+        let event = log.log_decode::<WavsNftMint>().ok()?.inner.data;
+        let fields = json!({
+            "to": event.to.to_string(),
+            "tokenId": event.tokenId.to_string(),
+            "tokenURI": event.tokenURI,
+            "triggerId": event.triggerId,
+        });
+        (WavsNftMint::SIGNATURE, fields)
+    } else if signature == WavsNftUpdate::SIGNATURE_HASH {
+        let event = log.log_decode::<WavsNftUpdate>().ok()?.inner.data;
+        let fields = json!({
+            "owner": event.owner.to_string(),
+            "tokenId": event.tokenId.to_string(),
+            "tokenURI": event.tokenURI,
+            "triggerId": event.triggerId,
+        });
+        (WavsNftUpdate::SIGNATURE, fields)
+    } else if signature == WavsNftReveal::SIGNATURE_HASH {
+        let event = log.log_decode::<WavsNftReveal>().ok()?.inner.data;
+        let fields = json!({
+            "owner": event.owner.to_string(),
+            "tokenId": event.tokenId.to_string(),
+            "tokenURI": event.tokenURI,
+            "triggerId": event.triggerId,
+        });
+        (WavsNftReveal::SIGNATURE, fields)
+    } else if signature == MintFulfilled::SIGNATURE_HASH {
+        let event = log.log_decode::<MintFulfilled>().ok()?.inner.data;
+        (MintFulfilled::SIGNATURE, json!({ "triggerId": event.triggerId }))
+    } else if signature == UpdateFulfilled::SIGNATURE_HASH {
+        let event = log.log_decode::<UpdateFulfilled>().ok()?.inner.data;
+        let fields = json!({
+            "triggerId": event.triggerId,
+            "tokenId": event.tokenId.to_string(),
+            "tokenURI": event.tokenURI,
+        });
+        (UpdateFulfilled::SIGNATURE, fields)
+    } else {
+        return None;
+    };
+
+    Some(json!({
+        "event": event.split('(').next().unwrap_or(event),
+        "contract": log.address().to_string(),
+        "blockNumber": log.block_number,
+        "transactionHash": log.transaction_hash.map(|hash| hash.to_string()),
+        "args": fields,
+    }))
+}
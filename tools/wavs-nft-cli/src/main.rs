@@ -0,0 +1,495 @@
+mod contracts;
+mod repin;
+mod stuck;
+
+use alloy_network::EthereumWallet;
+use alloy_primitives::{Address, Bytes, U256};
+use alloy_provider::{Provider, ProviderBuilder};
+use alloy_rpc_types::Filter;
+use alloy_signer_local::PrivateKeySigner;
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Parser, Subcommand};
+use contracts::{IWavsMinter, IWavsNft};
+use serde_json::{json, Value};
+use std::{path::PathBuf, time::Duration};
+use wavs_nft_metadata::{gateway, pin::Pinner, Report, Validator};
+
+/// Trigger mints and updates of WAVS NFTs and inspect their results.
+#[derive(Parser, Debug)]
+#[command(name = "wavs-nft", version, about)]
+struct Cli {
+    /// JSON-RPC endpoint of the chain the contracts are deployed on
+    #[arg(long, env = "RPC_URL", default_value = "http://localhost:8545", global = true)]
+    rpc_url: String,
+
+    /// Key signing mint and update transactions, the first anvil account by default
+    #[arg(
+        long,
+        env = "ANVIL_PRIVATE_KEY",
+        default_value = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        hide_default_value = true,
+        hide_env_values = true,
+        global = true
+    )]
+    private_key: String,
+
+    /// Address of the WavsMinter contract
+    #[arg(long, env = "WAVS_MINTER", global = true)]
+    minter: Option<Address>,
+
+    /// Address of the WavsNft contract
+    #[arg(long, env = "WAVS_NFT", global = true)]
+    nft: Option<Address>,
+
+    /// IPFS gateway resolving `ipfs://` URIs, repeat to fall back to others in order
+    #[arg(
+        long = "ipfs-gateway",
+        env = "IPFS_GATEWAYS",
+        value_delimiter = ',',
+        default_values = gateway::DEFAULT_GATEWAYS,
+        global = true
+    )]
+    ipfs_gateways: Vec<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Pay the mint price and trigger the generation of a new NFT
+    Mint {
+        /// The text prompt for AI generation
+        prompt: String,
+
+        /// ABI-encoded `WavsMintOptions`, as `0x` prefixed hex
+        #[arg(long, value_parser = parse_hex)]
+        options: Option<Bytes>,
+    },
+    /// Pay the update fee and trigger the regeneration of an NFT owned by the signer
+    Update {
+        token_id: U256,
+
+        /// The text prompt for AI generation
+        prompt: String,
+
+        /// ABI-encoded `WavsMintOptions`, as `0x` prefixed hex
+        #[arg(long, value_parser = parse_hex)]
+        options: Option<Bytes>,
+    },
+    /// Show the minter's receipt of a trigger
+    Receipt {
+        trigger_id: u64,
+
+        /// Show the receipt of an update trigger, which the NFT contract numbers
+        #[arg(long)]
+        update: bool,
+    },
+    /// Show the owner, token URI and metadata of an NFT
+    Token { token_id: U256 },
+    /// Print triggers and fulfillments of the minter and NFT contracts as they are emitted
+    Watch {
+        /// First block to print events from, the latest block by default
+        #[arg(long)]
+        from_block: Option<u64>,
+
+        /// Seconds between polls of the endpoint
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
+    },
+    /// Report triggers that never produced a token, with the raw input replaying them
+    Stuck {
+        /// Age in seconds after which a trigger without a result is reported
+        #[arg(long, default_value_t = 600)]
+        older_than: u64,
+
+        /// First block to scan, such as the deployment block
+        #[arg(long, default_value_t = 0)]
+        from_block: u64,
+
+        /// Largest block range requested at once
+        #[arg(long, default_value_t = 10_000)]
+        batch_size: u64,
+
+        /// WAVS name of the chain the contracts are on, as the raw input refers to it
+        #[arg(long, default_value = "local")]
+        chain_name: String,
+
+        /// Run every stuck trigger through this compiled component with `wavs-runner`,
+        /// adding the output to submit by hand to the report
+        #[arg(long)]
+        run: Option<PathBuf>,
+
+        /// The `wavs-runner` binary used by `--run`, by default the one built next to this
+        /// binary or else the one on `PATH`
+        #[arg(long, env = "WAVS_RUNNER")]
+        runner: Option<PathBuf>,
+    },
+    /// Check the metadata and image of every token, reporting broken or non-conformant ones
+    Validate {
+        /// Tokens to check, every token of the contract by default
+        token_ids: Vec<U256>,
+
+        /// Only check the metadata, without fetching and decoding images
+        #[arg(long)]
+        skip_images: bool,
+
+        /// Include tokens without problems in the report
+        #[arg(long)]
+        all: bool,
+    },
+    /// Pin the metadata and files of every token on another IPFS provider, checking their CIDs
+    Repin {
+        /// Tokens to re-pin, every token of the contract by default
+        token_ids: Vec<U256>,
+
+        /// `add` endpoint of the provider to pin on, such as Lighthouse's or a Kubo node's
+        #[arg(long, env = "TARGET_IPFS_API_URL", required_unless_present = "dry_run")]
+        api_url: Option<String>,
+
+        /// API key of the provider, sent as a bearer token
+        #[arg(long, env = "TARGET_IPFS_API_KEY", hide_env_values = true)]
+        api_key: Option<String>,
+
+        /// Only list the CIDs the tokens reference
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+fn required(address: Option<Address>, flag: &str, env: &str) -> Result<Address> {
+    address.ok_or_else(|| anyhow!("Missing contract address, pass --{} or set {}", flag, env))
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let signer: PrivateKeySigner = cli.private_key.parse().context("Invalid private key")?;
+    let rpc_url =
+        cli.rpc_url.parse().with_context(|| format!("Invalid RPC URL {}", cli.rpc_url))?;
+    let provider = ProviderBuilder::new().wallet(EthereumWallet::from(signer)).on_http(rpc_url);
+
+    match cli.command {
+        Command::Mint { prompt, options } => {
+            let minter = required(cli.minter, "minter", "WAVS_MINTER")?;
+            let price = contracts::call(&provider, minter, IWavsMinter::mintPriceCall {}).await?._0;
+            let receipt = match options {
+                Some(options) => {
+                    let call = IWavsMinter::triggerMintWithOptionsCall { prompt, options };
+                    contracts::send(&provider, minter, call, price).await?
+                }
+                None => {
+                    let call = IWavsMinter::triggerMintCall { prompt };
+                    contracts::send(&provider, minter, call, price).await?
+                }
+            };
+            let trigger = contracts::trigger_event(&receipt)?;
+            print_json(&json!({
+                "transactionHash": receipt.transaction_hash.to_string(),
+                "triggerId": trigger.triggerId,
+                "paid": price.to_string(),
+            }))
+        }
+        Command::Update { token_id, prompt, options } => {
+            let nft = required(cli.nft, "nft", "WAVS_NFT")?;
+            let fee = contracts::call(&provider, nft, IWavsNft::updateFeeCall {}).await?._0;
+            let receipt = match options {
+                Some(options) => {
+                    let call = IWavsNft::triggerUpdateWithOptionsCall {
+                        tokenId: token_id,
+                        prompt,
+                        options,
+                    };
+                    contracts::send(&provider, nft, call, fee).await?
+                }
+                None => {
+                    let call = IWavsNft::triggerUpdateCall { tokenId: token_id, prompt };
+                    contracts::send(&provider, nft, call, fee).await?
+                }
+            };
+            let trigger = contracts::trigger_event(&receipt)?;
+            print_json(&json!({
+                "transactionHash": receipt.transaction_hash.to_string(),
+                "triggerId": trigger.triggerId,
+                "tokenId": token_id.to_string(),
+                "paid": fee.to_string(),
+            }))
+        }
+        Command::Receipt { trigger_id, update: false } => {
+            let minter = required(cli.minter, "minter", "WAVS_MINTER")?;
+            let call = IWavsMinter::getTriggerCall { triggerId: trigger_id };
+            let receipt = contracts::call(&provider, minter, call).await?._0;
+            if receipt.creator == Address::ZERO {
+                return Err(anyhow!("No receipt for Trigger ID: {}", trigger_id));
+            }
+            print_json(&json!({
+                "triggerId": trigger_id,
+                "creator": receipt.creator.to_string(),
+                "prompt": receipt.prompt,
+                "wavsTriggerType": contracts::trigger_type_name(receipt.wavsTriggerType),
+                "fulfilled": receipt.fulfilled,
+                "tokenId": receipt.fulfilled.then(|| receipt.tokenId.to_string()),
+                "tokenURI": receipt.fulfilled.then_some(receipt.tokenURI),
+            }))
+        }
+        Command::Receipt { trigger_id, update: true } => {
+            let minter = required(cli.minter, "minter", "WAVS_MINTER")?;
+            let call = IWavsMinter::getUpdateReceiptCall { triggerId: trigger_id };
+            let receipt = contracts::call(&provider, minter, call).await?._0;
+            print_json(&json!({
+                "triggerId": trigger_id,
+                "fulfilled": receipt.fulfilled,
+                "tokenId": receipt.fulfilled.then(|| receipt.tokenId.to_string()),
+                "tokenURI": receipt.fulfilled.then_some(receipt.tokenURI),
+            }))
+        }
+        Command::Token { token_id } => {
+            let nft = required(cli.nft, "nft", "WAVS_NFT")?;
+            let owner =
+                contracts::call(&provider, nft, IWavsNft::ownerOfCall { tokenId: token_id })
+                    .await
+                    .with_context(|| format!("Token {} does not exist", token_id))?
+                    ._0;
+            let token_uri =
+                contracts::call(&provider, nft, IWavsNft::tokenURICall { tokenId: token_id })
+                    .await?
+                    ._0;
+
+            let client = reqwest::Client::new();
+            let (metadata, source) =
+                match gateway::fetch(&client, &token_uri, &cli.ipfs_gateways).await {
+                    Ok((bytes, source)) => match serde_json::from_slice::<Value>(&bytes) {
+                        Ok(metadata) => (metadata, Some(source)),
+                        Err(e) => {
+                            eprintln!("Token URI content is not JSON: {}", e);
+                            (Value::Null, Some(source))
+                        }
+                    },
+                    Err(e) => {
+                        eprintln!("{:#}", e);
+                        (Value::Null, None)
+                    }
+                };
+            // Link the image through the first gateway for viewing in a browser
+            let image_url = match (metadata["image"].as_str(), cli.ipfs_gateways.first()) {
+                (Some(image), Some(gateway)) if image.starts_with("ipfs://") => {
+                    Some(gateway::http_url(image, gateway))
+                }
+                (image, _) => image.map(str::to_string),
+            };
+
+            print_json(&json!({
+                "tokenId": token_id.to_string(),
+                "owner": owner.to_string(),
+                "tokenURI": token_uri,
+                "source": source,
+                "imageUrl": image_url,
+                "metadata": metadata,
+            }))
+        }
+        Command::Stuck { older_than, from_block, batch_size, chain_name, run, runner } => {
+            let minter = required(cli.minter, "minter", "WAVS_MINTER")?;
+            let nft = required(cli.nft, "nft", "WAVS_NFT")?;
+            let stuck =
+                stuck::find(&provider, minter, nft, from_block, batch_size, older_than).await?;
+            let chain_id = provider.get_chain_id().await?;
+            let runner = stuck::runner_path(runner);
+
+            let mut report = Vec::new();
+            for trigger in &stuck {
+                let mut entry = trigger.to_json(&chain_name)?;
+                if let Some(component) = &run {
+                    eprintln!("Running Trigger ID {} locally", trigger.event.triggerId);
+                    let raw = trigger.raw_input(&chain_name)?;
+                    entry["output"] = match stuck::run_locally(
+                        &runner,
+                        component,
+                        &chain_name,
+                        &cli.rpc_url,
+                        chain_id,
+                        nft,
+                        raw,
+                    ) {
+                        Ok(output) => output,
+                        Err(e) => json!({ "error": format!("{:#}", e) }),
+                    };
+                }
+                report.push(entry);
+            }
+            print_json(&Value::Array(report))
+        }
+        Command::Validate { token_ids, skip_images, all } => {
+            let nft = required(cli.nft, "nft", "WAVS_NFT")?;
+            let token_ids = if token_ids.is_empty() {
+                contracts::token_ids(&provider, nft).await?
+            } else {
+                token_ids
+            };
+
+            let mut validator = Validator::new(cli.ipfs_gateways);
+            validator.check_images = !skip_images;
+            let (mut broken, mut warned, mut tokens) = (0, 0, Vec::new());
+            for token_id in &token_ids {
+                eprintln!("Checking token {}", token_id);
+                let call = IWavsNft::tokenURICall { tokenId: *token_id };
+                let report = match contracts::call(&provider, nft, call).await {
+                    Ok(token_uri) => validator.validate(&token_uri._0).await,
+                    Err(e) => Report { errors: vec![format!("{:#}", e)], ..Default::default() },
+                };
+                if !report.is_ok() {
+                    broken += 1;
+                } else if !report.warnings.is_empty() {
+                    warned += 1;
+                } else if !all {
+                    continue;
+                }
+                let mut entry = json!(report);
+                entry["tokenId"] = json!(token_id.to_string());
+                tokens.push(entry);
+            }
+
+            print_json(&json!({
+                "checked": token_ids.len(),
+                "broken": broken,
+                "withWarnings": warned,
+                "tokens": tokens,
+            }))?;
+            if broken > 0 {
+                bail!("{} of {} tokens are broken or non-conformant", broken, token_ids.len());
+            }
+            Ok(())
+        }
+        Command::Repin { token_ids, api_url, api_key, dry_run } => {
+            let nft = required(cli.nft, "nft", "WAVS_NFT")?;
+            let token_ids = if token_ids.is_empty() {
+                contracts::token_ids(&provider, nft).await?
+            } else {
+                token_ids
+            };
+
+            let client = reqwest::Client::new();
+            let mut references = repin::References::default();
+            for token_id in token_ids {
+                eprintln!("Reading the metadata of token {}", token_id);
+                let call = IWavsNft::tokenURICall { tokenId: token_id };
+                match contracts::call(&provider, nft, call).await {
+                    Ok(token_uri) => {
+                        references
+                            .walk(
+                                &provider,
+                                nft,
+                                &client,
+                                &cli.ipfs_gateways,
+                                token_id,
+                                token_uri._0,
+                            )
+                            .await
+                    }
+                    Err(e) => references.errors.push(json!({
+                        "tokenId": token_id.to_string(),
+                        "error": format!("{:#}", e),
+                    })),
+                }
+            }
+
+            let pinner = match (dry_run, api_url) {
+                (false, Some(api_url)) => Some(Pinner::new(api_url, api_key)),
+                _ => None,
+            };
+            let (mut failed, mut cids) = (references.errors.len(), Vec::new());
+            for reference in references.by_cid.values() {
+                let mut entry = reference.to_json();
+                if let Some(pinner) = &pinner {
+                    eprintln!("Pinning {}", reference.uri);
+                    match reference.repin(&client, &cli.ipfs_gateways, pinner).await {
+                        Ok((pinned, bytes)) if reference.matches(&pinned) => {
+                            entry["status"] = json!("pinned");
+                            entry["pinnedCid"] = json!(pinned);
+                            entry["bytes"] = json!(bytes);
+                        }
+                        Ok((pinned, bytes)) => {
+                            failed += 1;
+                            entry["status"] = json!("mismatch");
+                            entry["pinnedCid"] = json!(pinned);
+                            entry["bytes"] = json!(bytes);
+                        }
+                        Err(e) => {
+                            failed += 1;
+                            entry["status"] = json!("failed");
+                            entry["error"] = json!(format!("{:#}", e));
+                        }
+                    }
+                }
+                cids.push(entry);
+            }
+
+            print_json(&json!({ "cids": cids, "errors": references.errors }))?;
+            if failed > 0 {
+                bail!("{} CIDs or tokens could not be re-pinned", failed);
+            }
+            Ok(())
+        }
+        Command::Watch { from_block, interval } => {
+            let addresses: Vec<Address> = cli.minter.into_iter().chain(cli.nft).collect();
+            if addresses.is_empty() {
+                return Err(anyhow!(
+                    "Nothing to watch, pass --minter or --nft, or set WAVS_MINTER or WAVS_NFT"
+                ));
+            }
+            let mut next = match from_block {
+                Some(block) => block,
+                None => provider.get_block_number().await?,
+            };
+            eprintln!("Watching {:?} from block {}", addresses, next);
+
+            loop {
+                let latest = provider.get_block_number().await?;
+                if latest >= next {
+                    let filter =
+                        Filter::new().address(addresses.clone()).from_block(next).to_block(latest);
+                    for log in provider.get_logs(&filter).await? {
+                        if let Some(event) = contracts::describe_log(&log) {
+                            println!("{}", serde_json::to_string(&event)?);
+                        }
+                    }
+                    next = latest + 1;
+                }
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+            }
+        }
+    }
+}
+
+/// Clap would otherwise take `Bytes: From<String>` and pass the hex text itself
+fn parse_hex(s: &str) -> Result<Bytes> {
+    s.parse().with_context(|| format!("expected 0x prefixed hex, got {}", s))
+}
+
+fn print_json(value: &Value) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_hex_options() {
+        let options = |args: &[&str]| match Cli::try_parse_from(args).unwrap().command {
+            Command::Mint { options, .. } | Command::Update { options, .. } => options,
+            command => panic!("unexpected command {:?}", command),
+        };
+        assert_eq!(
+            options(&["wavs-nft", "mint", "a lighthouse", "--options", "0x01ff"]),
+            Some(Bytes::from(vec![0x01, 0xff]))
+        );
+        assert_eq!(
+            options(&["wavs-nft", "update", "7", "a lighthouse", "--options", "0x0102"]),
+            Some(Bytes::from(vec![0x01, 0x02]))
+        );
+        assert_eq!(options(&["wavs-nft", "mint", "a lighthouse"]), None);
+        assert!(Cli::try_parse_from(["wavs-nft", "mint", "a", "--options", "lighthouse"]).is_err());
+    }
+}
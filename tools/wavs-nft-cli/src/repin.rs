@@ -0,0 +1,161 @@
+use crate::contracts;
+use alloy_primitives::{Address, B256, U256};
+use alloy_provider::Provider;
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use wavs_nft_metadata::{
+    gateway,
+    pin::{cid_from_uri, filename_of, same_content, Pinner},
+};
+
+/// A CID referenced by tokens, read through the first URI it was seen under
+pub struct Reference {
+    pub cid: String,
+    pub uri: String,
+    pub token_ids: BTreeSet<U256>,
+    content: Option<Vec<u8>>,
+}
+
+/// Every CID the metadata of a set of tokens references, keyed by CID
+#[derive(Default)]
+pub struct References {
+    pub by_cid: BTreeMap<String, Reference>,
+    /// Metadata that could not be read, so its files are missing from the list
+    pub errors: Vec<Value>,
+}
+
+impl References {
+    /// Record the CIDs of a token's metadata and of the files it links to, walking
+    /// `previous` back to the original mint so the history of the token keeps resolving.
+    /// Revisions kept in data URIs are read back from the NFT event that set them.
+    pub async fn walk<P: Provider>(
+        &mut self,
+        provider: &P,
+        nft: Address,
+        client: &reqwest::Client,
+        gateways: &[String],
+        token_id: U256,
+        token_uri: String,
+    ) {
+        let mut queue = vec![token_uri];
+        let mut seen = BTreeSet::new();
+        while let Some(uri) = queue.pop() {
+            if !seen.insert(uri.clone()) {
+                continue;
+            }
+            let metadata = match self.read(client, gateways, token_id, &uri).await {
+                Ok(bytes) => serde_json::from_slice::<Value>(&bytes)
+                    .with_context(|| format!("{} is not JSON", uri)),
+                Err(e) => Err(e),
+            };
+            let metadata = match metadata {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    self.errors.push(json!({
+                        "tokenId": token_id.to_string(),
+                        "uri": uri,
+                        "error": format!("{:#}", e),
+                    }));
+                    continue;
+                }
+            };
+
+            let files = metadata["properties"]["files"].as_array().into_iter().flatten();
+            let links = [&metadata["image"], &metadata["animation_url"]]
+                .into_iter()
+                .chain(files.map(|file| &file["uri"]))
+                .filter_map(Value::as_str);
+            for link in links {
+                self.add(token_id, link);
+            }
+            let previous = &metadata["previous"];
+            if let Some(previous) = previous["token_uri"].as_str() {
+                queue.push(previous.to_string());
+            } else if previous["set_by"].is_object() {
+                match set_by(provider, nft, &previous["set_by"]).await {
+                    Ok(previous) => queue.push(previous),
+                    Err(e) => self.errors.push(json!({
+                        "tokenId": token_id.to_string(),
+                        "uri": uri,
+                        "error": format!("Failed to read the previous revision: {:#}", e),
+                    })),
+                }
+            }
+        }
+    }
+
+    /// Read metadata, fetched once however many tokens or revisions reference it
+    async fn read(
+        &mut self,
+        client: &reqwest::Client,
+        gateways: &[String],
+        token_id: U256,
+        uri: &str,
+    ) -> Result<Vec<u8>> {
+        // Metadata held in the token URI itself has no CID but can link to pinned files
+        let Some(reference) = self.add(token_id, uri) else {
+            return Ok(gateway::fetch(client, uri, gateways).await?.0);
+        };
+        if let Some(content) = &reference.content {
+            return Ok(content.clone());
+        }
+        let (content, _) = gateway::fetch(client, uri, gateways).await?;
+        reference.content = Some(content.clone());
+        Ok(content)
+    }
+
+    fn add(&mut self, token_id: U256, uri: &str) -> Option<&mut Reference> {
+        let cid = cid_from_uri(uri)?;
+        let reference = self.by_cid.entry(cid.clone()).or_insert_with(|| Reference {
+            cid,
+            uri: uri.to_string(),
+            token_ids: BTreeSet::new(),
+            content: None,
+        });
+        reference.token_ids.insert(token_id);
+        Some(reference)
+    }
+}
+
+/// The token URI set by the event a `previous.set_by` entry points at
+async fn set_by<P: Provider>(provider: &P, nft: Address, set_by: &Value) -> Result<String> {
+    let transaction_hash = set_by["transaction_hash"]
+        .as_str()
+        .ok_or_else(|| anyhow!("set_by has no transaction_hash"))?
+        .parse::<B256>()?;
+    let log_index =
+        set_by["log_index"].as_u64().ok_or_else(|| anyhow!("set_by has no log_index"))?;
+    contracts::token_uri_set_by(provider, nft, transaction_hash, log_index).await
+}
+
+impl Reference {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "cid": self.cid,
+            "uri": self.uri,
+            "tokenIds": self.token_ids.iter().map(U256::to_string).collect::<Vec<_>>(),
+        })
+    }
+
+    /// Fetch the content if it was not read while walking, pin it under the name the
+    /// URI gives it and check the provider assigned it the CID tokens refer to
+    pub async fn repin(
+        &self,
+        client: &reqwest::Client,
+        gateways: &[String],
+        pinner: &Pinner,
+    ) -> Result<(String, usize)> {
+        let content = match &self.content {
+            Some(content) => content.clone(),
+            None => gateway::fetch(client, &self.uri, gateways).await?.0,
+        };
+        let pinned = pinner.pin(&filename_of(&self.uri, &self.cid), &content).await?;
+        Ok((pinned, content.len()))
+    }
+
+    /// Whether the CID the provider assigned addresses the same content
+    pub fn matches(&self, pinned: &str) -> bool {
+        same_content(&self.cid, pinned)
+    }
+}
@@ -0,0 +1,164 @@
+use crate::contracts::{
+    trigger_type_name,
+    IWavsNftServiceTypes::{
+        WavsNftMint, WavsNftReveal, WavsNftTrigger, WavsNftUpdate, WavsRawTrigger,
+    },
+};
+use alloy_primitives::{Address, Bytes, B256};
+use alloy_provider::Provider;
+use alloy_rpc_types::{BlockNumberOrTag, BlockTransactionsKind, Filter, Log};
+use alloy_sol_types::{SolEvent, SolValue};
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A trigger that never produced its `WavsNftMint`, `WavsNftUpdate` or `WavsNftReveal`
+pub struct StuckTrigger {
+    pub log: Log,
+    pub event: WavsNftTrigger,
+    pub age: u64,
+}
+
+impl StuckTrigger {
+    /// Input of the component's raw trigger path replaying the event
+    pub fn raw_input(&self, chain_name: &str) -> Result<Vec<u8>> {
+        let raw = WavsRawTrigger {
+            chainName: chain_name.to_string(),
+            emitter: self.log.address(),
+            blockNumber: self.log.block_number.ok_or_else(|| anyhow!("Log without a block"))?,
+            topics: self.log.topics().to_vec(),
+            data: self.log.data().data.clone(),
+        };
+        Ok(raw.abi_encode())
+    }
+
+    pub fn to_json(&self, chain_name: &str) -> Result<Value> {
+        Ok(json!({
+            "emitter": self.log.address().to_string(),
+            "triggerId": self.event.triggerId,
+            "wavsTriggerType": trigger_type_name(self.event.wavsTriggerType),
+            "sender": self.event.sender.to_string(),
+            "prompt": self.event.prompt,
+            "tokenId": self.event.tokenId.to_string(),
+            "blockNumber": self.log.block_number,
+            "transactionHash": self.log.transaction_hash.map(|hash| hash.to_string()),
+            "ageSeconds": self.age,
+            "raw": Bytes::from(self.raw_input(chain_name)?).to_string(),
+        }))
+    }
+}
+
+/// Scan both contracts for triggers older than `older_than` seconds without a result.
+///
+/// Mint triggers are numbered by the minter and answered by `WavsNftMint`, update and
+/// reveal triggers are numbered by the NFT contract and answered by `WavsNftUpdate`
+/// and `WavsNftReveal`.
+pub async fn find<P: Provider>(
+    provider: &P,
+    minter: Address,
+    nft: Address,
+    from_block: u64,
+    batch_size: u64,
+    older_than: u64,
+) -> Result<Vec<StuckTrigger>> {
+    let latest = block_timestamp(provider, BlockNumberOrTag::Latest).await?;
+    let head = provider.get_block_number().await?;
+
+    let signatures: Vec<B256> = vec![
+        WavsNftTrigger::SIGNATURE_HASH,
+        WavsNftMint::SIGNATURE_HASH,
+        WavsNftUpdate::SIGNATURE_HASH,
+        WavsNftReveal::SIGNATURE_HASH,
+    ];
+    let mut triggers = Vec::new();
+    let mut answered = HashSet::new();
+    let mut next = from_block;
+    while next <= head {
+        let to = head.min(next + batch_size.max(1) - 1);
+        let filter = Filter::new()
+            .address(vec![minter, nft])
+            .event_signature(signatures.clone())
+            .from_block(next)
+            .to_block(to);
+        for log in provider.get_logs(&filter).await? {
+            let Some(signature) = log.topic0().copied() else { continue };
+            if signature == WavsNftTrigger::SIGNATURE_HASH {
+                let event = log.log_decode::<WavsNftTrigger>()?.inner.data;
+                triggers.push((log, event));
+            } else if signature == WavsNftMint::SIGNATURE_HASH {
+                answered.insert((minter, log.log_decode::<WavsNftMint>()?.inner.data.triggerId));
+            } else if signature == WavsNftUpdate::SIGNATURE_HASH {
+                answered.insert((nft, log.log_decode::<WavsNftUpdate>()?.inner.data.triggerId));
+            } else if signature == WavsNftReveal::SIGNATURE_HASH {
+                answered.insert((nft, log.log_decode::<WavsNftReveal>()?.inner.data.triggerId));
+            }
+        }
+        next = to + 1;
+    }
+
+    let mut stuck = Vec::new();
+    for (log, event) in triggers {
+        if answered.contains(&(log.address(), event.triggerId)) {
+            continue;
+        }
+        let block = log.block_number.ok_or_else(|| anyhow!("Log without a block"))?;
+        let age = latest.saturating_sub(block_timestamp(provider, block.into()).await?);
+        if age >= older_than {
+            stuck.push(StuckTrigger { log, event, age });
+        }
+    }
+    Ok(stuck)
+}
+
+async fn block_timestamp<P: Provider>(provider: &P, block: BlockNumberOrTag) -> Result<u64> {
+    let block = provider
+        .get_block_by_number(block, BlockTransactionsKind::Hashes)
+        .await?
+        .ok_or_else(|| anyhow!("Block {} not found", block))?;
+    Ok(block.header.timestamp)
+}
+
+/// The `wavs-runner` binary: `explicit` when given, otherwise the one built next to this
+/// binary, as `cargo build` puts every workspace binary in the same directory, and finally
+/// the one on `PATH`
+pub fn runner_path(explicit: Option<PathBuf>) -> PathBuf {
+    explicit
+        .or_else(|| {
+            let sibling = std::env::current_exe()
+                .ok()?
+                .with_file_name(format!("wavs-runner{}", std::env::consts::EXE_SUFFIX));
+            sibling.is_file().then_some(sibling)
+        })
+        .unwrap_or_else(|| PathBuf::from("wavs-runner"))
+}
+
+/// Run the raw input through a compiled component with the local runner, returning its JSON output.
+/// The chain is configured under the name the raw input refers to, with its real ID, so the
+/// provenance in the metadata matches what an operator would have written.
+pub fn run_locally(
+    runner: &Path,
+    component: &Path,
+    chain_name: &str,
+    rpc_url: &str,
+    chain_id: u64,
+    nft: Address,
+    raw: Vec<u8>,
+) -> Result<Value> {
+    let output = Command::new(runner)
+        .arg("--component")
+        .arg(component)
+        .arg("--chain")
+        .arg(format!("{}={}@{}", chain_name, rpc_url, chain_id))
+        .arg("--env")
+        .arg(format!("nft_contract={}", nft))
+        .arg("raw")
+        .arg(Bytes::from(raw).to_string())
+        .output()
+        .with_context(|| format!("Failed to start wavs-runner at {}", runner.display()))?;
+    if !output.status.success() {
+        bail!("wavs-runner failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    serde_json::from_slice(&output.stdout).context("wavs-runner printed invalid JSON")
+}
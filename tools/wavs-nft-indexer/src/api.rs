@@ -0,0 +1,81 @@
+use crate::db::{SharedDb, TriggerFilter};
+use alloy_primitives::Address;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde_json::{json, Value};
+use tower_http::cors::CorsLayer;
+
+/// An error answered as `{"error": ...}` with its status code
+pub struct ApiError(StatusCode, String);
+
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type ApiResult = Result<Json<Value>, ApiError>;
+
+/// Routes of the query API, open to any origin so the frontend can call it from the browser
+pub fn router(db: SharedDb) -> Router {
+    Router::new()
+        .route("/status", get(status))
+        .route("/stats", get(stats))
+        .route("/triggers", get(triggers))
+        .route("/triggers/{source}/{trigger_id}", get(trigger))
+        .route("/tokens/{token_id}/triggers", get(token_triggers))
+        .layer(CorsLayer::permissive())
+        .with_state(db)
+}
+
+async fn status(State(db): State<SharedDb>) -> ApiResult {
+    let cursor = db.lock().unwrap().cursor()?;
+    Ok(Json(json!({ "indexedBlock": cursor })))
+}
+
+async fn stats(State(db): State<SharedDb>) -> ApiResult {
+    Ok(Json(db.lock().unwrap().stats()?))
+}
+
+async fn triggers(
+    State(db): State<SharedDb>,
+    Query(mut filter): Query<TriggerFilter>,
+) -> ApiResult {
+    // Addresses are stored checksummed
+    if let Some(sender) = &filter.sender {
+        let sender: Address = sender
+            .parse()
+            .map_err(|e| ApiError(StatusCode::BAD_REQUEST, format!("Invalid sender: {}", e)))?;
+        filter.sender = Some(sender.to_string());
+    }
+    let triggers = db.lock().unwrap().triggers(&filter)?;
+    Ok(Json(json!(triggers)))
+}
+
+async fn trigger(
+    State(db): State<SharedDb>,
+    Path((source, trigger_id)): Path<(String, u64)>,
+) -> ApiResult {
+    let db = db.lock().unwrap();
+    let trigger = db.trigger(&source, trigger_id)?.ok_or_else(|| {
+        ApiError(StatusCode::NOT_FOUND, format!("No {} trigger {}", source, trigger_id))
+    })?;
+    let events = db.trigger_events(&source, trigger_id)?;
+    Ok(Json(json!({ "trigger": trigger, "events": events })))
+}
+
+async fn token_triggers(State(db): State<SharedDb>, Path(token_id): Path<String>) -> ApiResult {
+    let triggers = db.lock().unwrap().token_triggers(&token_id)?;
+    Ok(Json(json!(triggers)))
+}
@@ -0,0 +1,439 @@
+use anyhow::Result;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Blocks whose hash is kept to detect reorgs, older ones are considered final
+const REORG_DEPTH: u64 = 1024;
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS events (
+    block_number INTEGER NOT NULL,
+    block_hash TEXT NOT NULL,
+    block_timestamp INTEGER NOT NULL,
+    transaction_hash TEXT NOT NULL,
+    log_index INTEGER NOT NULL,
+    contract TEXT NOT NULL,
+    event TEXT NOT NULL,
+    source TEXT NOT NULL,
+    trigger_id INTEGER NOT NULL,
+    trigger_type TEXT,
+    token_id TEXT,
+    account TEXT,
+    prompt TEXT,
+    token_uri TEXT,
+    PRIMARY KEY (transaction_hash, log_index)
+);
+CREATE INDEX IF NOT EXISTS events_trigger ON events (source, trigger_id);
+CREATE INDEX IF NOT EXISTS events_block ON events (block_number);
+
+CREATE TABLE IF NOT EXISTS blocks (
+    number INTEGER PRIMARY KEY,
+    hash TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS state (
+    key TEXT PRIMARY KEY,
+    value INTEGER NOT NULL
+);
+
+-- A trigger is generated once the NFT contract emitted its result, and fulfilled
+-- once the minter recorded it. Reveals have no minter record.
+CREATE VIEW IF NOT EXISTS trigger_status AS
+SELECT
+    t.source,
+    t.trigger_id,
+    t.trigger_type,
+    t.account AS sender,
+    t.prompt,
+    t.token_id,
+    t.block_number,
+    t.transaction_hash,
+    t.block_timestamp AS triggered_at,
+    r.generated_at,
+    f.fulfilled_at,
+    COALESCE(r.tokens, '[]') AS tokens,
+    CASE
+        WHEN f.fulfilled_at IS NOT NULL THEN 'fulfilled'
+        WHEN r.generated_at IS NOT NULL THEN 'generated'
+        ELSE 'pending'
+    END AS status,
+    r.generated_at - t.block_timestamp AS generation_seconds,
+    f.fulfilled_at - t.block_timestamp AS fulfillment_seconds
+FROM events t
+LEFT JOIN (
+    SELECT source, trigger_id, MIN(block_timestamp) AS generated_at,
+        json_group_array(token_id) AS tokens
+    FROM events
+    WHERE event IN ('WavsNftMint', 'WavsNftUpdate', 'WavsNftReveal')
+    GROUP BY source, trigger_id
+) r ON r.source = t.source AND r.trigger_id = t.trigger_id
+LEFT JOIN (
+    SELECT source, trigger_id, MIN(block_timestamp) AS fulfilled_at
+    FROM events
+    WHERE event IN ('MintFulfilled', 'UpdateFulfilled', 'WavsNftReveal')
+    GROUP BY source, trigger_id
+) f ON f.source = t.source AND f.trigger_id = t.trigger_id
+WHERE t.event = 'WavsNftTrigger';
+"#;
+
+/// An event of the minter or NFT contract, flattened to the columns the indexer queries on
+#[derive(Debug, Clone, Default)]
+pub struct Event {
+    pub block_number: u64,
+    pub block_hash: String,
+    pub block_timestamp: u64,
+    pub transaction_hash: String,
+    pub log_index: u64,
+    pub contract: String,
+    pub event: &'static str,
+    /// Contract numbering the trigger, `minter` for mints and `nft` for updates and reveals
+    pub source: &'static str,
+    pub trigger_id: u64,
+    pub trigger_type: Option<&'static str>,
+    pub token_id: Option<String>,
+    /// Sender of a trigger, recipient of a mint or owner of an updated token
+    pub account: Option<String>,
+    pub prompt: Option<String>,
+    pub token_uri: Option<String>,
+}
+
+/// Lifecycle of a trigger, computed from its events
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggerStatus {
+    pub source: String,
+    pub trigger_id: u64,
+    pub trigger_type: Option<String>,
+    pub sender: Option<String>,
+    pub prompt: Option<String>,
+    /// Token being updated or revealed, none for mints
+    pub token_id: Option<String>,
+    pub block_number: u64,
+    pub transaction_hash: String,
+    pub triggered_at: u64,
+    pub generated_at: Option<u64>,
+    pub fulfilled_at: Option<u64>,
+    /// Tokens the trigger produced or updated
+    pub tokens: Vec<String>,
+    pub status: String,
+    pub generation_seconds: Option<i64>,
+    pub fulfillment_seconds: Option<i64>,
+}
+
+impl TriggerStatus {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let tokens: String = row.get("tokens")?;
+        Ok(TriggerStatus {
+            source: row.get("source")?,
+            trigger_id: row.get("trigger_id")?,
+            trigger_type: row.get("trigger_type")?,
+            sender: row.get("sender")?,
+            prompt: row.get("prompt")?,
+            token_id: row.get("token_id")?,
+            block_number: row.get("block_number")?,
+            transaction_hash: row.get("transaction_hash")?,
+            triggered_at: row.get("triggered_at")?,
+            generated_at: row.get("generated_at")?,
+            fulfilled_at: row.get("fulfilled_at")?,
+            tokens: serde_json::from_str(&tokens).unwrap_or_default(),
+            status: row.get("status")?,
+            generation_seconds: row.get("generation_seconds")?,
+            fulfillment_seconds: row.get("fulfillment_seconds")?,
+        })
+    }
+}
+
+/// Filters of the trigger listing, all optional
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TriggerFilter {
+    pub status: Option<String>,
+    pub source: Option<String>,
+    pub sender: Option<String>,
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+}
+
+/// The database shared by the indexer and the query API
+pub type SharedDb = Arc<Mutex<Db>>;
+
+pub struct Db {
+    conn: Connection,
+}
+
+impl Db {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        Self::init(conn)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Db { conn })
+    }
+
+    /// Last indexed block, none before the first sync
+    pub fn cursor(&self) -> Result<Option<u64>> {
+        Ok(self
+            .conn
+            .query_row("SELECT value FROM state WHERE key = 'cursor'", [], |row| row.get(0))
+            .optional()?)
+    }
+
+    /// Stored block hashes, most recent first
+    pub fn recent_blocks(&self) -> Result<Vec<(u64, String)>> {
+        let mut statement =
+            self.conn.prepare("SELECT number, hash FROM blocks ORDER BY number DESC")?;
+        let blocks = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(blocks.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Store the events and block hashes of an indexed range and move the cursor to its end
+    pub fn apply(&mut self, events: &[Event], blocks: &[(u64, String)], cursor: u64) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        for event in events {
+            tx.execute(
+                "INSERT OR REPLACE INTO events (block_number, block_hash, block_timestamp,
+                    transaction_hash, log_index, contract, event, source, trigger_id,
+                    trigger_type, token_id, account, prompt, token_uri)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                params![
+                    event.block_number,
+                    event.block_hash,
+                    event.block_timestamp,
+                    event.transaction_hash,
+                    event.log_index,
+                    event.contract,
+                    event.event,
+                    event.source,
+                    event.trigger_id,
+                    event.trigger_type,
+                    event.token_id,
+                    event.account,
+                    event.prompt,
+                    event.token_uri,
+                ],
+            )?;
+        }
+        for (number, hash) in blocks {
+            tx.execute(
+                "INSERT OR REPLACE INTO blocks (number, hash) VALUES (?1, ?2)",
+                params![number, hash],
+            )?;
+        }
+        tx.execute(
+            "DELETE FROM blocks WHERE number < ?1",
+            params![cursor.saturating_sub(REORG_DEPTH)],
+        )?;
+        tx.execute(
+            "INSERT OR REPLACE INTO state (key, value) VALUES ('cursor', ?1)",
+            params![cursor],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Drop everything indexed after `block`, or everything when it is none
+    pub fn rewind(&mut self, block: Option<u64>) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        match block {
+            Some(block) => {
+                tx.execute("DELETE FROM events WHERE block_number > ?1", params![block])?;
+                tx.execute("DELETE FROM blocks WHERE number > ?1", params![block])?;
+                tx.execute(
+                    "INSERT OR REPLACE INTO state (key, value) VALUES ('cursor', ?1)",
+                    params![block],
+                )?;
+            }
+            None => {
+                tx.execute_batch("DELETE FROM events; DELETE FROM blocks; DELETE FROM state;")?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn triggers(&self, filter: &TriggerFilter) -> Result<Vec<TriggerStatus>> {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        for (column, value) in
+            [("status", &filter.status), ("source", &filter.source), ("sender", &filter.sender)]
+        {
+            if let Some(value) = value {
+                values.push(value.clone());
+                conditions.push(format!("{} = ?{}", column, values.len()));
+            }
+        }
+        let mut sql = "SELECT * FROM trigger_status".to_string();
+        if !conditions.is_empty() {
+            sql.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        }
+        sql.push_str(&format!(
+            " ORDER BY triggered_at DESC, source, trigger_id DESC LIMIT {} OFFSET {}",
+            filter.limit.unwrap_or(100),
+            filter.offset.unwrap_or(0)
+        ));
+
+        let mut statement = self.conn.prepare(&sql)?;
+        let triggers = statement.query_map(params_from_iter(values), TriggerStatus::from_row)?;
+        Ok(triggers.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn trigger(&self, source: &str, trigger_id: u64) -> Result<Option<TriggerStatus>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT * FROM trigger_status WHERE source = ?1 AND trigger_id = ?2",
+                params![source, trigger_id],
+                TriggerStatus::from_row,
+            )
+            .optional()?)
+    }
+
+    /// Triggers that targeted or produced a token
+    pub fn token_triggers(&self, token_id: &str) -> Result<Vec<TriggerStatus>> {
+        let mut statement = self.conn.prepare(
+            "SELECT * FROM trigger_status
+            WHERE token_id = ?1 OR EXISTS (SELECT 1 FROM json_each(tokens) WHERE value = ?1)
+            ORDER BY triggered_at, source, trigger_id",
+        )?;
+        let triggers = statement.query_map(params![token_id], TriggerStatus::from_row)?;
+        Ok(triggers.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Events of a trigger in chain order
+    pub fn trigger_events(&self, source: &str, trigger_id: u64) -> Result<Vec<Value>> {
+        let mut statement = self.conn.prepare(
+            "SELECT event, contract, block_number, block_timestamp, transaction_hash, log_index,
+                token_id, account, token_uri
+            FROM events WHERE source = ?1 AND trigger_id = ?2
+            ORDER BY block_number, log_index",
+        )?;
+        let events = statement.query_map(params![source, trigger_id], |row| {
+            Ok(json!({
+                "event": row.get::<_, String>(0)?,
+                "contract": row.get::<_, String>(1)?,
+                "blockNumber": row.get::<_, u64>(2)?,
+                "blockTimestamp": row.get::<_, u64>(3)?,
+                "transactionHash": row.get::<_, String>(4)?,
+                "logIndex": row.get::<_, u64>(5)?,
+                "tokenId": row.get::<_, Option<String>>(6)?,
+                "account": row.get::<_, Option<String>>(7)?,
+                "tokenURI": row.get::<_, Option<String>>(8)?,
+            }))
+        })?;
+        Ok(events.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Trigger counts and latencies per status
+    pub fn stats(&self) -> Result<Value> {
+        let mut statement = self.conn.prepare(
+            "SELECT status, COUNT(*), AVG(generation_seconds), MAX(generation_seconds),
+                AVG(fulfillment_seconds), MAX(fulfillment_seconds), MIN(triggered_at)
+            FROM trigger_status GROUP BY status",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                json!({
+                    "count": row.get::<_, u64>(1)?,
+                    "avgGenerationSeconds": row.get::<_, Option<f64>>(2)?,
+                    "maxGenerationSeconds": row.get::<_, Option<i64>>(3)?,
+                    "avgFulfillmentSeconds": row.get::<_, Option<f64>>(4)?,
+                    "maxFulfillmentSeconds": row.get::<_, Option<i64>>(5)?,
+                    "oldestTriggeredAt": row.get::<_, Option<u64>>(6)?,
+                }),
+            ))
+        })?;
+        let stats = rows.collect::<rusqlite::Result<serde_json::Map<_, _>>>()?;
+        Ok(Value::Object(stats))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db() -> Db {
+        Db::init(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    fn event(
+        event: &'static str,
+        source: &'static str,
+        block_number: u64,
+        log_index: u64,
+    ) -> Event {
+        Event {
+            block_number,
+            block_hash: format!("0x{:064x}", block_number),
+            block_timestamp: 1_000 + block_number * 2,
+            transaction_hash: format!("0x{:064x}", block_number * 100 + log_index),
+            log_index,
+            event,
+            source,
+            trigger_id: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn mint_goes_from_pending_to_fulfilled() {
+        let mut db = db();
+        let trigger =
+            Event { trigger_type: Some("MINT"), ..event("WavsNftTrigger", "minter", 1, 0) };
+        db.apply(&[trigger], &[], 1).unwrap();
+        assert_eq!(db.trigger("minter", 1).unwrap().unwrap().status, "pending");
+
+        let mint =
+            Event { token_id: Some("7".to_string()), ..event("WavsNftMint", "minter", 3, 0) };
+        db.apply(&[mint], &[], 3).unwrap();
+        let status = db.trigger("minter", 1).unwrap().unwrap();
+        assert_eq!(status.status, "generated");
+        assert_eq!(status.generation_seconds, Some(4));
+        assert_eq!(status.tokens, vec!["7"]);
+
+        db.apply(&[event("MintFulfilled", "minter", 4, 0)], &[], 4).unwrap();
+        let status = db.trigger("minter", 1).unwrap().unwrap();
+        assert_eq!(status.status, "fulfilled");
+        assert_eq!(status.fulfillment_seconds, Some(6));
+        assert_eq!(db.token_triggers("7").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn trigger_ids_are_numbered_per_contract() {
+        let mut db = db();
+        let mint = event("WavsNftTrigger", "minter", 1, 0);
+        let update = event("WavsNftTrigger", "nft", 1, 1);
+        db.apply(&[mint, update, event("WavsNftUpdate", "nft", 2, 0)], &[], 2).unwrap();
+
+        assert_eq!(db.trigger("minter", 1).unwrap().unwrap().status, "pending");
+        assert_eq!(db.trigger("nft", 1).unwrap().unwrap().status, "generated");
+        let filter = TriggerFilter { status: Some("pending".to_string()), ..Default::default() };
+        assert_eq!(db.triggers(&filter).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn rewind_drops_events_after_the_fork() {
+        let mut db = db();
+        let blocks = [(1, "0xa".to_string()), (3, "0xb".to_string())];
+        db.apply(
+            &[event("WavsNftTrigger", "minter", 1, 0), event("WavsNftMint", "minter", 3, 0)],
+            &blocks,
+            3,
+        )
+        .unwrap();
+
+        db.rewind(Some(2)).unwrap();
+        assert_eq!(db.cursor().unwrap(), Some(2));
+        assert_eq!(db.recent_blocks().unwrap(), vec![(1, "0xa".to_string())]);
+        assert_eq!(db.trigger("minter", 1).unwrap().unwrap().status, "pending");
+
+        db.rewind(None).unwrap();
+        assert_eq!(db.cursor().unwrap(), None);
+        assert!(db.trigger("minter", 1).unwrap().is_none());
+    }
+}
@@ -0,0 +1,97 @@
+mod api;
+mod db;
+mod sync;
+
+use alloy_primitives::Address;
+use alloy_provider::RootProvider;
+use anyhow::{Context, Result};
+use clap::Parser;
+use db::Db;
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use sync::Indexer;
+
+/// Index the trigger lifecycle of the WAVS NFT contracts into SQLite and serve it over HTTP.
+#[derive(Parser, Debug)]
+#[command(name = "wavs-nft-indexer", version, about)]
+struct Cli {
+    /// JSON-RPC endpoint of the chain the contracts are deployed on
+    #[arg(long, env = "RPC_URL", default_value = "http://localhost:8545")]
+    rpc_url: String,
+
+    /// Address of the WavsMinter contract
+    #[arg(long, env = "WAVS_MINTER")]
+    minter: Address,
+
+    /// Address of the WavsNft contract
+    #[arg(long, env = "WAVS_NFT")]
+    nft: Address,
+
+    /// SQLite database, created if missing
+    #[arg(long, default_value = "wavs-nft-index.db")]
+    database: PathBuf,
+
+    /// Block to start indexing from on an empty database, such as the deployment block
+    #[arg(long, default_value_t = 0)]
+    from_block: u64,
+
+    /// Largest block range requested at once
+    #[arg(long, default_value_t = 1000)]
+    batch_size: u64,
+
+    /// Blocks to stay behind the head of the chain. Reorgs within the last
+    /// indexed blocks are detected and rewound either way.
+    #[arg(long, default_value_t = 0)]
+    confirmations: u64,
+
+    /// Seconds between polls of the endpoint
+    #[arg(long, default_value_t = 2)]
+    interval: u64,
+
+    /// Address the query API listens on
+    #[arg(long, default_value = "127.0.0.1:8090")]
+    listen: SocketAddr,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let db = Arc::new(Mutex::new(
+        Db::open(&cli.database)
+            .with_context(|| format!("Failed to open {}", cli.database.display()))?,
+    ));
+    let rpc_url =
+        cli.rpc_url.parse().with_context(|| format!("Invalid RPC URL {}", cli.rpc_url))?;
+    let indexer = Indexer {
+        provider: RootProvider::new_http(rpc_url),
+        db: db.clone(),
+        minter: cli.minter,
+        nft: cli.nft,
+        from_block: cli.from_block,
+        batch_size: cli.batch_size,
+        confirmations: cli.confirmations,
+    };
+
+    // Endpoint errors are retried on the next poll
+    let interval = Duration::from_secs(cli.interval);
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = indexer.sync().await {
+                eprintln!("Indexing failed: {:#}", e);
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+
+    let listener = tokio::net::TcpListener::bind(cli.listen)
+        .await
+        .with_context(|| format!("Failed to listen on {}", cli.listen))?;
+    eprintln!("Serving the query API on http://{}", cli.listen);
+    axum::serve(listener, api::router(db)).await?;
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use crate::db::{Event, SharedDb};
+use alloy_primitives::Address;
+use alloy_provider::{Provider, RootProvider};
+use alloy_rpc_types::{BlockNumberOrTag, BlockTransactionsKind, Filter, Log};
+use alloy_sol_macro::sol;
+use alloy_sol_types::SolEvent;
+use anyhow::{anyhow, bail, Result};
+use std::collections::{btree_map::Entry, BTreeMap};
+
+sol!("../../src/interfaces/IWavsNftServiceTypes.sol");
+
+use IWavsNftServiceTypes::{
+    MintFulfilled, UpdateFulfilled, WavsNftMint, WavsNftReveal, WavsNftTrigger, WavsNftUpdate,
+};
+
+/// Follows the minter and NFT contracts into the database
+pub struct Indexer {
+    pub provider: RootProvider,
+    pub db: SharedDb,
+    pub minter: Address,
+    pub nft: Address,
+    /// Block to start from on an empty database
+    pub from_block: u64,
+    /// Largest block range requested with `eth_getLogs`
+    pub batch_size: u64,
+    /// Blocks to stay behind the head of the chain
+    pub confirmations: u64,
+}
+
+impl Indexer {
+    /// Index every confirmed block not indexed yet
+    pub async fn sync(&self) -> Result<()> {
+        self.handle_reorg().await?;
+
+        let head = self.provider.get_block_number().await?.saturating_sub(self.confirmations);
+        let cursor = self.db.lock().unwrap().cursor()?;
+        let mut next = cursor.map_or(self.from_block, |cursor| cursor + 1);
+        while next <= head {
+            let to = head.min(next + self.batch_size.max(1) - 1);
+            self.index_range(next, to).await?;
+            next = to + 1;
+        }
+        Ok(())
+    }
+
+    /// Rewind to the last stored block still on the chain, undoing events of abandoned forks
+    async fn handle_reorg(&self) -> Result<()> {
+        let stored = self.db.lock().unwrap().recent_blocks()?;
+        for (i, (number, hash)) in stored.iter().enumerate() {
+            let (chain_hash, _) = self.block(*number).await?;
+            if chain_hash == *hash {
+                if i > 0 {
+                    eprintln!("Reorg detected, rewinding to block {}", number);
+                    self.db.lock().unwrap().rewind(Some(*number))?;
+                }
+                return Ok(());
+            }
+        }
+        if let Some((oldest, _)) = stored.last() {
+            eprintln!("Reorg deeper than the stored blocks, reindexing from block {}", oldest);
+            self.db.lock().unwrap().rewind(oldest.checked_sub(1))?;
+        }
+        Ok(())
+    }
+
+    async fn index_range(&self, from: u64, to: u64) -> Result<()> {
+        let filter =
+            Filter::new().address(vec![self.minter, self.nft]).from_block(from).to_block(to);
+        let logs = self.provider.get_logs(&filter).await?;
+
+        let mut blocks = BTreeMap::new();
+        let mut events = Vec::new();
+        for log in &logs {
+            let number = log.block_number.ok_or_else(|| anyhow!("Log without a block number"))?;
+            if let Entry::Vacant(entry) = blocks.entry(number) {
+                entry.insert(self.block(number).await?);
+            }
+            let (hash, timestamp) = &blocks[&number];
+            // The block was replaced between the two requests, the next poll starts over
+            if log.block_hash.map(|hash| hash.to_string()).as_ref() != Some(hash) {
+                bail!("Block {} changed while indexing", number);
+            }
+            if let Some(event) = self.decode(log, hash, *timestamp) {
+                events.push(event);
+            }
+        }
+        // The end of the range is what the next poll checks for reorgs
+        if let Entry::Vacant(entry) = blocks.entry(to) {
+            entry.insert(self.block(to).await?);
+        }
+
+        let blocks: Vec<(u64, String)> =
+            blocks.into_iter().map(|(number, (hash, _))| (number, hash)).collect();
+        self.db.lock().unwrap().apply(&events, &blocks, to)?;
+        if !events.is_empty() {
+            eprintln!("Indexed {} events up to block {}", events.len(), to);
+        }
+        Ok(())
+    }
+
+    /// Hash and timestamp of a block
+    async fn block(&self, number: u64) -> Result<(String, u64)> {
+        let block = self
+            .provider
+            .get_block_by_number(BlockNumberOrTag::Number(number), BlockTransactionsKind::Hashes)
+            .await?
+            .ok_or_else(|| anyhow!("Block {} not found", number))?;
+        Ok((block.header.hash.to_string(), block.header.timestamp))
+    }
+
+    /// Flatten a log of the two contracts, `None` for events that are not part of a trigger's lifecycle
+    fn decode(&self, log: &Log, block_hash: &str, block_timestamp: u64) -> Option<Event> {
+        let contract = log.address();
+        let base = Event {
+            block_number: log.block_number?,
+            block_hash: block_hash.to_string(),
+            block_timestamp,
+            transaction_hash: log.transaction_hash?.to_string(),
+            log_index: log.log_index?,
+            contract: contract.to_string(),
+            ..Default::default()
+        };
+
+        let signature = *log.topic0()?;
+        let event = if signature == WavsNftTrigger::SIGNATURE_HASH {
+            let event = log.log_decode::<WavsNftTrigger>().ok()?.inner.data;
+            let trigger_type = match event.wavsTriggerType {
+                0 => "MINT",
+                1 => "UPDATE",
+                2 => "BATCH_MINT",
+                3 => "REVEAL",
+                _ => "UNKNOWN",
+            };
+            // The token ID of a mint trigger is ignored by the AVS
+            let token_id =
+                matches!(trigger_type, "UPDATE" | "REVEAL").then(|| event.tokenId.to_string());
+            Event {
+                event: "WavsNftTrigger",
+                source: if contract == self.minter { "minter" } else { "nft" },
+                trigger_id: event.triggerId,
+                trigger_type: Some(trigger_type),
+                token_id,
+                account: Some(event.sender.to_string()),
+                prompt: Some(event.prompt),
+                ..base
+            }
+        } else if signature == WavsNftMint::SIGNATURE_HASH {
+            let event = log.log_decode::<WavsNftMint>().ok()?.inner.data;
+            Event {
+                event: "WavsNftMint",
+                source: "minter",
+                trigger_id: event.triggerId,
+                token_id: Some(event.tokenId.to_string()),
+                account: Some(event.to.to_string()),
+                token_uri: Some(event.tokenURI),
+                ..base
+            }
+        } else if signature == WavsNftUpdate::SIGNATURE_HASH {
+            let event = log.log_decode::<WavsNftUpdate>().ok()?.inner.data;
+            Event {
+                event: "WavsNftUpdate",
+                source: "nft",
+                trigger_id: event.triggerId,
+                token_id: Some(event.tokenId.to_string()),
+                account: Some(event.owner.to_string()),
+                token_uri: Some(event.tokenURI),
+                ..base
+            }
+        } else if signature == WavsNftReveal::SIGNATURE_HASH {
+            let event = log.log_decode::<WavsNftReveal>().ok()?.inner.data;
+            Event {
+                event: "WavsNftReveal",
+                source: "nft",
+                trigger_id: event.triggerId,
+                token_id: Some(event.tokenId.to_string()),
+                account: Some(event.owner.to_string()),
+                token_uri: Some(event.tokenURI),
+                ..base
+            }
+        } else if signature == MintFulfilled::SIGNATURE_HASH {
+            let event = log.log_decode::<MintFulfilled>().ok()?.inner.data;
+            Event { event: "MintFulfilled", source: "minter", trigger_id: event.triggerId, ..base }
+        } else if signature == UpdateFulfilled::SIGNATURE_HASH {
+            let event = log.log_decode::<UpdateFulfilled>().ok()?.inner.data;
+            Event {
+                event: "UpdateFulfilled",
+                source: "nft",
+                trigger_id: event.triggerId,
+                token_id: Some(event.tokenId.to_string()),
+                token_uri: Some(event.tokenURI),
+                ..base
+            }
+        } else {
+            return None;
+        };
+        Some(event)
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine;
+
+/// Public gateways tried in order when none are configured
+pub const DEFAULT_GATEWAYS: [&str; 2] =
+    ["https://gateway.lighthouse.storage/ipfs/", "https://ipfs.io/ipfs/"];
+
+/// HTTP URL of an `ipfs://` URI through a gateway, other URIs are returned as they are
+pub fn http_url(uri: &str, gateway: &str) -> String {
+    match uri.strip_prefix("ipfs://") {
+        Some(path) => format!("{}/{}", gateway.trim_end_matches('/'), path),
+        None => uri.to_string(),
+    }
+}
+
+/// Resolve a token or image URI to its content and where it was read from.
+///
+/// `ipfs://` URIs are tried against each gateway in order until one answers,
+/// `data:` URIs are decoded in place and `http(s)://` URIs are fetched directly.
+pub async fn fetch(
+    client: &reqwest::Client,
+    uri: &str,
+    gateways: &[String],
+) -> Result<(Vec<u8>, String)> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (media_type, payload) =
+            data.split_once(',').ok_or_else(|| anyhow!("Invalid data URI"))?;
+        let bytes = if media_type.ends_with(";base64") {
+            base64::engine::general_purpose::STANDARD.decode(payload)?
+        } else {
+            payload.as_bytes().to_vec()
+        };
+        return Ok((bytes, "data".to_string()));
+    }
+
+    if !uri.starts_with("ipfs://") {
+        return Ok((get(client, uri).await?, uri.to_string()));
+    }
+    if gateways.is_empty() {
+        bail!("No IPFS gateway configured to resolve {}", uri);
+    }
+
+    let mut errors = Vec::new();
+    for gateway in gateways {
+        let url = http_url(uri, gateway);
+        match get(client, &url).await {
+            Ok(bytes) => return Ok((bytes, url)),
+            Err(e) => errors.push(format!("{}: {:#}", url, e)),
+        }
+    }
+    bail!("Failed to resolve {} through any gateway:\n{}", uri, errors.join("\n"))
+}
+
+async fn get(client: &reqwest::Client, url: &str) -> Result<Vec<u8>> {
+    let response =
+        client.get(url).send().await.with_context(|| format!("Failed to fetch {}", url))?;
+    if !response.status().is_success() {
+        bail!("Failed to fetch {}. Status: {}", url, response.status());
+    }
+    Ok(response.bytes().await?.to_vec())
+}
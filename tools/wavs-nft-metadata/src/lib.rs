@@ -0,0 +1,154 @@
+//! Resolve the metadata of WAVS NFTs and check it against the schema the
+//! autonomous-artist component writes, along with the image it points to,
+//! and pin the files it references on IPFS providers.
+
+pub mod gateway;
+pub mod pin;
+pub mod schema;
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Outcome of checking the metadata behind a token URI
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    pub token_uri: String,
+    /// Where the metadata was read from
+    pub source: Option<String>,
+    pub image: Option<ImageInfo>,
+    /// Problems making the token broken or non-conformant
+    pub errors: Vec<String>,
+    /// Problems worth fixing that do not break the token
+    pub warnings: Vec<String>,
+}
+
+impl Report {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// The image of a token, as resolved and decoded
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageInfo {
+    /// Where the image was read from, `image_data` for inline SVG
+    pub source: String,
+    pub content_type: String,
+    pub bytes: usize,
+    /// Dimensions of raster images
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+/// Resolves token URIs through IPFS gateways and checks what they point to
+pub struct Validator {
+    pub client: reqwest::Client,
+    /// Gateways `ipfs://` URIs are tried against in order
+    pub gateways: Vec<String>,
+    /// Fetch and decode the image of every token, not only its metadata
+    pub check_images: bool,
+}
+
+impl Validator {
+    pub fn new(gateways: Vec<String>) -> Self {
+        Validator { client: reqwest::Client::new(), gateways, check_images: true }
+    }
+
+    /// Resolve a token URI, check its metadata against the schema and its image decodes
+    pub async fn validate(&self, token_uri: &str) -> Report {
+        let mut report = Report { token_uri: token_uri.to_string(), ..Default::default() };
+
+        let metadata = match gateway::fetch(&self.client, token_uri, &self.gateways).await {
+            Ok((bytes, source)) => {
+                report.source = Some(source);
+                match serde_json::from_slice::<Value>(&bytes) {
+                    Ok(metadata) => metadata,
+                    Err(e) => {
+                        report.errors.push(format!("Token URI content is not JSON: {}", e));
+                        return report;
+                    }
+                }
+            }
+            Err(e) => {
+                report.errors.push(format!("{:#}", e));
+                return report;
+            }
+        };
+        report.errors.extend(schema::check(&metadata));
+
+        let image = metadata["image"].as_str().filter(|image| !image.is_empty());
+        if image.is_some_and(|image| image.starts_with("data:")) {
+            // The component embeds the image when its IPFS upload fails
+            report.warnings.push(
+                "Image is embedded as a data URI rather than pinned, the IPFS upload likely failed"
+                    .to_string(),
+            );
+        }
+        if !self.check_images {
+            return report;
+        }
+
+        match (image, metadata["image_data"].as_str()) {
+            (Some(image), _) => match self.check_image(image).await {
+                Ok(info) => report.image = Some(info),
+                Err(e) => report.errors.push(e),
+            },
+            (None, Some(image_data)) if schema::is_svg(image_data) => {
+                report.image = Some(ImageInfo {
+                    source: "image_data".to_string(),
+                    content_type: "image/svg+xml".to_string(),
+                    bytes: image_data.len(),
+                    width: None,
+                    height: None,
+                });
+            }
+            // Missing or malformed images are reported by the schema check
+            _ => {}
+        }
+        report
+    }
+
+    async fn check_image(&self, uri: &str) -> Result<ImageInfo, String> {
+        let (bytes, source) = gateway::fetch(&self.client, uri, &self.gateways)
+            .await
+            .map_err(|e| format!("Image does not resolve: {:#}", e))?;
+        let (content_type, dimensions) = decode_image(&bytes)?;
+
+        // A data URI declaring another type is rendered wrong or not at all
+        if let Some(data) = uri.strip_prefix("data:") {
+            let declared = data.split([';', ',']).next().unwrap_or_default();
+            if declared != content_type {
+                return Err(format!(
+                    "Image data URI declares {} but holds {}",
+                    declared, content_type
+                ));
+            }
+        }
+
+        Ok(ImageInfo {
+            source,
+            content_type,
+            bytes: bytes.len(),
+            width: dimensions.map(|(width, _)| width),
+            height: dimensions.map(|(_, height)| height),
+        })
+    }
+}
+
+/// Decode an image, returning its MIME type and the dimensions of raster images
+pub fn decode_image(bytes: &[u8]) -> Result<(String, Option<(u32, u32)>), String> {
+    if bytes.is_empty() {
+        return Err("Image is empty".to_string());
+    }
+    if schema::is_svg(&String::from_utf8_lossy(&bytes[..bytes.len().min(256)])) {
+        return Ok(("image/svg+xml".to_string(), None));
+    }
+
+    let format =
+        image::guess_format(bytes).map_err(|e| format!("Image format is not recognized: {}", e))?;
+    let decoded = image::load_from_memory_with_format(bytes, format)
+        .map_err(|e| format!("Image does not decode: {}", e))?;
+    Ok((format.to_mime_type().to_string(), Some((decoded.width(), decoded.height()))))
+}
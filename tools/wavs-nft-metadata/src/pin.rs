@@ -0,0 +1,116 @@
+use anyhow::{anyhow, bail, Context, Result};
+use cid::Cid;
+use serde::Deserialize;
+use wavs_nft_ipfs::AddRequest;
+
+/// An IPFS `add` endpoint pinning what is uploaded to it, such as Lighthouse's or a
+/// Kubo node's, called the way the autonomous-artist component uploads images and metadata
+pub struct Pinner {
+    pub client: reqwest::Client,
+    pub api_url: String,
+    /// Sent as a bearer token, as the component sends its Lighthouse API key
+    pub api_key: Option<String>,
+}
+
+impl Pinner {
+    pub fn new(api_url: String, api_key: Option<String>) -> Self {
+        Pinner { client: reqwest::Client::new(), api_url, api_key }
+    }
+
+    /// Upload a file as a multipart form and return the CID the endpoint assigned to it.
+    /// The file is sent under the MIME type of its extension.
+    ///
+    /// Endpoints answering one line per added entry, as Kubo does with
+    /// `wrap-with-directory=true`, return the CID of the last entry, the directory.
+    pub async fn pin(&self, filename: &str, content: &[u8]) -> Result<String> {
+        let form = AddRequest::new(filename, wavs_nft_ipfs::content_type(filename), content);
+        let mut request = self
+            .client
+            .post(&self.api_url)
+            .header("Content-Type", form.content_type)
+            .body(form.body);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Failed to upload to {}", self.api_url))?;
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            bail!("Failed to upload to IPFS. Status: {}, Body: {}", status, text);
+        }
+
+        // Lighthouse and Kubo answer with capitalized fields
+        #[derive(Deserialize)]
+        struct AddResponse {
+            #[serde(rename = "Hash", alias = "hash")]
+            hash: String,
+        }
+
+        let last = text
+            .lines()
+            .rfind(|line| !line.trim().is_empty())
+            .ok_or_else(|| anyhow!("Empty response from {}", self.api_url))?;
+        let added: AddResponse = serde_json::from_str(last)
+            .with_context(|| format!("Could not extract hash from response: {}", text))?;
+        Ok(added.hash)
+    }
+}
+
+/// Extract the CID from an `ipfs://CID[/path]` URI or a gateway URL containing `/ipfs/CID`
+pub fn cid_from_uri(uri: &str) -> Option<String> {
+    let rest = match uri.strip_prefix("ipfs://") {
+        Some(rest) => rest,
+        None => &uri[uri.find("/ipfs/")? + "/ipfs/".len()..],
+    };
+    let cid = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    (!cid.is_empty()).then(|| cid.to_string())
+}
+
+/// Name of the file a URI points to within its CID, the CID itself when there is no path
+pub fn filename_of(uri: &str, cid: &str) -> String {
+    let path = uri.split_once(cid).map(|(_, path)| path).unwrap_or_default();
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    match path.rsplit('/').next() {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => cid.to_string(),
+    }
+}
+
+/// Whether two CIDs address the same content. A CIDv0 and the CIDv1 of
+/// the same DAG are equal, as they only differ in their encoding.
+pub fn same_content(a: &str, b: &str) -> bool {
+    match (Cid::try_from(a), Cid::try_from(b)) {
+        (Ok(a), Ok(b)) => a.codec() == b.codec() && a.hash() == b.hash(),
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_cids_and_filenames() {
+        let cid = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+        let uri = format!("ipfs://{}/nft_metadata.json", cid);
+        assert_eq!(cid_from_uri(&uri).as_deref(), Some(cid));
+        assert_eq!(filename_of(&uri, cid), "nft_metadata.json");
+
+        let url = format!("https://ipfs.io/ipfs/{}?filename=x", cid);
+        assert_eq!(cid_from_uri(&url).as_deref(), Some(cid));
+        assert_eq!(filename_of(&url, cid), cid);
+        assert_eq!(cid_from_uri("https://example.com/image.png"), None);
+    }
+
+    #[test]
+    fn compares_cid_versions() {
+        let v0 = "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG";
+        let v1 = "bafybeie5nqv6kd3qnfjupgvz34woh3oksc3iau6abmyajn7qvtf6d2ho34";
+        assert!(same_content(v0, v1));
+        assert!(same_content(v0, v0));
+        assert!(!same_content(v0, "bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy"));
+    }
+}
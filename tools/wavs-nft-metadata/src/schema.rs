@@ -0,0 +1,400 @@
+use serde_json::{Map, Value};
+use url::Url;
+
+/// URL schemes accepted for links in the metadata, as in the component
+const URL_SCHEMES: [&str; 5] = ["ipfs", "https", "http", "ar", "data"];
+
+/// Values of `display_type` marketplaces render
+const DISPLAY_TYPES: [&str; 4] = ["number", "boost_percentage", "boost_number", "date"];
+
+/// Whether a document is a raw SVG, as `image_data` must be
+pub fn is_svg(text: &str) -> bool {
+    let text = text.trim_start();
+    text.starts_with("<svg") || text.starts_with("<?xml")
+}
+
+/// Check metadata against the `NFTMetadata` schema of the autonomous-artist component.
+///
+/// Applies the same rules as `NFTMetadata::validate`, plus the types of every field,
+/// and returns every problem found rather than stopping at the first.
+pub fn check(metadata: &Value) -> Vec<String> {
+    let Some(fields) = metadata.as_object() else {
+        return vec!["Metadata must be a JSON object".to_string()];
+    };
+    let mut errors = Vec::new();
+
+    if let Some(name) = string_field(fields, "name", true, &mut errors) {
+        if name.trim().is_empty() {
+            errors.push("Metadata name must not be empty".to_string());
+        }
+    }
+    string_field(fields, "description", true, &mut errors);
+
+    let image = string_field(fields, "image", false, &mut errors).filter(|image| !image.is_empty());
+    let image_data = string_field(fields, "image_data", false, &mut errors);
+    if image.is_none() && image_data.is_none() {
+        errors.push("Metadata must have an image or image_data".to_string());
+    }
+    if let Some(image) = image {
+        check_url("image", image, &mut errors);
+    }
+    if image_data.is_some_and(|image_data| !is_svg(image_data)) {
+        errors.push("Metadata image_data must be a raw SVG document".to_string());
+    }
+
+    for field in ["external_url", "animation_url", "youtube_url"] {
+        if let Some(value) = string_field(fields, field, false, &mut errors) {
+            check_url(field, value, &mut errors);
+        }
+    }
+
+    if let Some(color) = string_field(fields, "background_color", false, &mut errors) {
+        if color.len() != 6 || !color.chars().all(|c| c.is_ascii_hexdigit()) {
+            errors.push(format!(
+                "Metadata background_color must be six hexadecimal characters without '#', got {}",
+                color
+            ));
+        }
+    }
+
+    match fields.get("attributes") {
+        Some(Value::Array(attributes)) => {
+            for attribute in attributes {
+                check_attribute(attribute, &mut errors);
+            }
+        }
+        Some(_) => errors.push("Metadata attributes must be an array".to_string()),
+        None => errors.push("Metadata attributes is missing".to_string()),
+    }
+
+    match fields.get("properties") {
+        None | Some(Value::Null) => {}
+        Some(Value::Object(properties)) => match properties.get("files") {
+            None | Some(Value::Null) => {}
+            Some(Value::Array(files)) => {
+                for file in files {
+                    check_file(file, &mut errors);
+                }
+            }
+            Some(_) => errors.push("Metadata properties.files must be an array".to_string()),
+        },
+        Some(_) => errors.push("Metadata properties must be an object".to_string()),
+    }
+
+    match fields.get("revision") {
+        None | Some(Value::Null) => {}
+        Some(revision) if revision.is_u64() => {}
+        Some(_) => errors.push("Metadata revision must be a non-negative integer".to_string()),
+    }
+
+    match fields.get("previous") {
+        None | Some(Value::Null) => {}
+        // Prior metadata kept in a data URI is linked by the event that set it and the
+        // digest of its canonical JSON
+        Some(Value::Object(previous)) => {
+            let token_uri = string_field(previous, "token_uri", false, &mut errors);
+            if let Some(token_uri) = token_uri {
+                check_url("previous.token_uri", token_uri, &mut errors);
+            }
+            string_field(previous, "cid", false, &mut errors);
+            if let Some(digest) = string_field(previous, "digest", false, &mut errors) {
+                check_hash("previous.digest", digest, &mut errors);
+            }
+            let set_by = match previous.get("set_by") {
+                None | Some(Value::Null) => None,
+                Some(Value::Object(set_by)) => Some(set_by),
+                Some(_) => {
+                    errors.push("Metadata previous.set_by must be an object".to_string());
+                    None
+                }
+            };
+            if let Some(set_by) = set_by {
+                for field in ["block_number", "log_index"] {
+                    if !set_by.get(field).is_some_and(Value::is_u64) {
+                        errors.push(format!(
+                            "Metadata previous.set_by.{} must be a non-negative integer",
+                            field
+                        ));
+                    }
+                }
+                match set_by.get("transaction_hash").and_then(Value::as_str) {
+                    Some(hash) => check_hash("previous.set_by.transaction_hash", hash, &mut errors),
+                    None => errors.push(
+                        "Metadata previous.set_by.transaction_hash must be a string".to_string(),
+                    ),
+                }
+            }
+            if token_uri.is_none() && set_by.is_none() {
+                errors.push("Metadata previous must have a token_uri or set_by".to_string());
+            }
+        }
+        Some(_) => errors.push("Metadata previous must be an object".to_string()),
+    }
+
+    errors
+}
+
+/// Read a string field, recording an error if it has another type, or is missing and required
+fn string_field<'a>(
+    fields: &'a Map<String, Value>,
+    field: &str,
+    required: bool,
+    errors: &mut Vec<String>,
+) -> Option<&'a str> {
+    match fields.get(field) {
+        Some(Value::String(value)) => Some(value),
+        None | Some(Value::Null) => {
+            if required {
+                errors.push(format!("Metadata {} is missing", field));
+            }
+            None
+        }
+        Some(_) => {
+            errors.push(format!("Metadata {} must be a string", field));
+            None
+        }
+    }
+}
+
+fn check_hash(field: &str, value: &str, errors: &mut Vec<String>) {
+    let hex = value.strip_prefix("0x").unwrap_or_default();
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        errors.push(format!("Metadata {} must be a 32 byte hex string", field));
+    }
+}
+
+fn check_url(field: &str, value: &str, errors: &mut Vec<String>) {
+    match Url::parse(value) {
+        Ok(url) if URL_SCHEMES.contains(&url.scheme()) => {}
+        Ok(url) => {
+            errors.push(format!("Metadata {} has unsupported scheme {}", field, url.scheme()))
+        }
+        Err(e) => errors.push(format!("Metadata {} is not a valid URL: {}", field, e)),
+    }
+}
+
+fn check_attribute(attribute: &Value, errors: &mut Vec<String>) {
+    let Some(fields) = attribute.as_object() else {
+        errors.push("Attribute must be a JSON object".to_string());
+        return;
+    };
+    let trait_type = match fields.get("trait_type") {
+        Some(Value::String(trait_type)) if !trait_type.trim().is_empty() => trait_type,
+        _ => {
+            errors.push("Attribute trait_type must be a non-empty string".to_string());
+            return;
+        }
+    };
+    let number = match fields.get("value") {
+        Some(Value::Number(n)) => n.as_f64(),
+        Some(Value::String(_)) => None,
+        _ => {
+            errors.push(format!("Attribute {} value must be a string or a number", trait_type));
+            return;
+        }
+    };
+
+    let display_type = match fields.get("display_type") {
+        None | Some(Value::Null) => None,
+        Some(Value::String(display_type)) if DISPLAY_TYPES.contains(&display_type.as_str()) => {
+            Some(display_type.as_str())
+        }
+        Some(display_type) => {
+            errors.push(format!(
+                "Attribute {} has unknown display_type {}",
+                trait_type, display_type
+            ));
+            return;
+        }
+    };
+    if let Some(display_type) = display_type {
+        match number {
+            None => errors.push(format!(
+                "Attribute {} with display_type {} must have a numeric value",
+                trait_type, display_type
+            )),
+            Some(n) if display_type == "date" && (n < 0.0 || n.fract() != 0.0) => {
+                errors.push(format!("Attribute {} must be a unix timestamp in seconds", trait_type))
+            }
+            Some(_) => {}
+        }
+    }
+
+    match fields.get("max_value") {
+        None | Some(Value::Null) => {}
+        Some(Value::Number(max_value)) => {
+            let max_value = max_value.as_f64().unwrap_or(f64::NAN);
+            if display_type == Some("date") {
+                errors.push(format!(
+                    "Attribute {} with display_type date cannot have a max_value",
+                    trait_type
+                ));
+            } else if let Some(n) = number {
+                if n > max_value {
+                    errors.push(format!(
+                        "Attribute {} value {} exceeds max_value {}",
+                        trait_type, n, max_value
+                    ));
+                }
+            } else {
+                errors.push(format!(
+                    "Attribute {} with max_value must have a numeric value",
+                    trait_type
+                ));
+            }
+        }
+        Some(_) => errors.push(format!("Attribute {} max_value must be a number", trait_type)),
+    }
+}
+
+fn check_file(file: &Value, errors: &mut Vec<String>) {
+    let Some(fields) = file.as_object() else {
+        errors.push("Metadata properties.files entries must be objects".to_string());
+        return;
+    };
+    if let Some(uri) = string_field(fields, "uri", true, errors) {
+        check_url("properties.files", uri, errors);
+    }
+    string_field(fields, "type", true, errors);
+    string_field(fields, "name", true, errors);
+    for dimension in ["width", "height"] {
+        let valid =
+            fields.get(dimension).and_then(Value::as_u64).is_some_and(|n| n <= u32::MAX as u64);
+        if !valid {
+            errors.push(format!(
+                "Metadata properties.files {} must be a non-negative integer",
+                dimension
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn accepts_generated_metadata() {
+        let metadata = json!({
+            "name": "WAVS NFT #1",
+            "description": "A painting",
+            "image": "ipfs://bafkreigh2akiscaildcqabsyg3dfr6chu3fgpregiymsck7e7aqa4s52zy",
+            "attributes": [
+                { "trait_type": "Palette", "value": "warm" },
+                { "display_type": "date", "trait_type": "Minted", "value": 1700000000 },
+                { "display_type": "boost_percentage", "trait_type": "Chaos", "value": 40, "max_value": 100 },
+            ],
+            "properties": { "files": [
+                { "uri": "ipfs://thumbnail", "type": "image/webp", "name": "thumbnail", "width": 256, "height": 256 },
+            ] },
+            "revision": 1,
+            "previous": { "token_uri": "ipfs://previous" },
+        });
+        assert_eq!(check(&metadata), Vec::<String>::new());
+
+        let on_chain = json!({
+            "name": "On-chain",
+            "description": "",
+            "image_data": "<svg xmlns=\"http://www.w3.org/2000/svg\"/>",
+            "attributes": [],
+        });
+        assert_eq!(check(&on_chain), Vec::<String>::new());
+    }
+
+    #[test]
+    fn reports_every_problem() {
+        let metadata = json!({
+            "name": " ",
+            "image": "ftp://example.com/image.png",
+            "background_color": "#ffffff",
+            "attributes": {},
+            "revision": -1,
+        });
+        let errors = check(&metadata);
+        assert_eq!(errors.len(), 6, "{:?}", errors);
+        assert!(errors.contains(&"Metadata name must not be empty".to_string()));
+        assert!(errors.contains(&"Metadata description is missing".to_string()));
+        assert!(errors.contains(&"Metadata image has unsupported scheme ftp".to_string()));
+
+        assert_eq!(
+            check(&json!({ "name": "x", "description": "", "attributes": [] })),
+            vec!["Metadata must have an image or image_data"]
+        );
+        assert_eq!(
+            check(
+                &json!({ "name": "x", "description": "", "image_data": "<html/>", "attributes": [] })
+            ),
+            vec!["Metadata image_data must be a raw SVG document"]
+        );
+    }
+
+    #[test]
+    fn checks_previous_revisions() {
+        let check_previous = |previous: Value| {
+            check(&json!({
+                "name": "x",
+                "description": "",
+                "image": "ipfs://image",
+                "attributes": [],
+                "revision": 2,
+                "previous": previous,
+            }))
+        };
+        let hash = format!("0x{}", "ab".repeat(32));
+        let set_by = json!({ "block_number": 42, "transaction_hash": hash, "log_index": 3 });
+        assert_eq!(
+            check_previous(json!({ "digest": hash, "set_by": set_by })),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            check_previous(json!({ "digest": hash })),
+            vec!["Metadata previous must have a token_uri or set_by"]
+        );
+        assert_eq!(
+            check_previous(json!({ "digest": "0x1234", "set_by": set_by })),
+            vec!["Metadata previous.digest must be a 32 byte hex string"]
+        );
+        assert_eq!(
+            check_previous(json!({ "set_by": { "block_number": -1, "transaction_hash": "0x" } })),
+            vec![
+                "Metadata previous.set_by.block_number must be a non-negative integer",
+                "Metadata previous.set_by.log_index must be a non-negative integer",
+                "Metadata previous.set_by.transaction_hash must be a 32 byte hex string",
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_attributes_like_the_component() {
+        let check_one = |attribute: Value| {
+            check(&json!({
+                "name": "x",
+                "description": "",
+                "image": "ipfs://image",
+                "attributes": [attribute],
+            }))
+        };
+
+        assert_eq!(
+            check_one(json!({ "display_type": "date", "trait_type": "Minted", "value": 1.5 })),
+            vec!["Attribute Minted must be a unix timestamp in seconds"]
+        );
+        assert_eq!(
+            check_one(json!({ "display_type": "number", "trait_type": "Level", "value": "high" })),
+            vec!["Attribute Level with display_type number must have a numeric value"]
+        );
+        assert_eq!(
+            check_one(json!({ "trait_type": "Level", "value": 11, "max_value": 10 })),
+            vec!["Attribute Level value 11 exceeds max_value 10"]
+        );
+        assert_eq!(
+            check_one(json!({ "trait_type": "", "value": 1 })),
+            vec!["Attribute trait_type must be a non-empty string"]
+        );
+        assert_eq!(
+            check_one(json!({ "display_type": "stars", "trait_type": "Level", "value": 1 })),
+            vec!["Attribute Level has unknown display_type \"stars\""]
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use wasmtime_wasi_http::{
+    bindings::http::types::ErrorCode,
+    body::HyperOutgoingBody,
+    types::{default_send_request_handler, IncomingResponse, OutgoingRequestConfig},
+};
+
+/// Fixture file holding the HTTP exchanges recorded for each trigger
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Fixture {
+    pub triggers: BTreeMap<String, Vec<Exchange>>,
+}
+
+/// A single outbound HTTP request and the response it received
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Exchange {
+    pub method: String,
+    pub uri: String,
+    pub request_body: Body,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub response_body: Body,
+}
+
+/// HTTP body, stored as text when it is valid UTF-8 so fixtures stay readable
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Body {
+    Text(String),
+    Base64(String),
+}
+
+impl Body {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Body::Text(text.to_string()),
+            Err(_) => Body::Base64(base64::engine::general_purpose::STANDARD.encode(bytes)),
+        }
+    }
+
+    fn to_bytes(&self) -> Result<Bytes> {
+        match self {
+            Body::Text(text) => Ok(Bytes::from(text.clone())),
+            Body::Base64(data) => {
+                Ok(Bytes::from(base64::engine::general_purpose::STANDARD.decode(data)?))
+            }
+        }
+    }
+}
+
+impl Fixture {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = std::fs::read(path)
+            .with_context(|| format!("Failed to read fixture {}", path.display()))?;
+        Ok(serde_json::from_slice(&file)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write fixture {}", path.display()))
+    }
+}
+
+/// How outbound HTTP requests from the component are handled
+#[derive(Clone, Default)]
+pub enum HttpMode {
+    /// Send requests to the network
+    #[default]
+    Live,
+    /// Send requests to the network and capture every exchange
+    Record(Arc<Recorder>),
+    /// Serve previously recorded exchanges without touching the network
+    Replay(Arc<Replayer>),
+}
+
+pub struct Recorder {
+    path: PathBuf,
+    key: String,
+    exchanges: Mutex<Vec<Exchange>>,
+}
+
+impl Recorder {
+    pub fn new(path: PathBuf, key: String) -> Self {
+        Recorder { path, key, exchanges: Mutex::new(Vec::new()) }
+    }
+
+    pub async fn send(
+        &self,
+        request: hyper::Request<HyperOutgoingBody>,
+        config: OutgoingRequestConfig,
+    ) -> Result<IncomingResponse, ErrorCode> {
+        let (parts, body) = request.into_parts();
+        let request_body = body.collect().await?.to_bytes();
+        let method = parts.method.to_string();
+        let uri = parts.uri.to_string();
+
+        let request = hyper::Request::from_parts(parts, full_body(request_body.clone()));
+        let response = default_send_request_handler(request, config).await?;
+
+        let (parts, body) = response.resp.into_parts();
+        let response_body = body.collect().await?.to_bytes();
+
+        self.exchanges.lock().unwrap().push(Exchange {
+            method,
+            uri,
+            request_body: Body::from_bytes(&request_body),
+            status: parts.status.as_u16(),
+            headers: parts
+                .headers
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            response_body: Body::from_bytes(&response_body),
+        });
+
+        Ok(IncomingResponse {
+            resp: hyper::Response::from_parts(parts, full_body(response_body)),
+            worker: response.worker,
+            between_bytes_timeout: response.between_bytes_timeout,
+        })
+    }
+
+    /// Write the recorded exchanges to the fixture file, replacing any previous
+    /// recording for the same trigger
+    pub fn save(&self) -> Result<()> {
+        let mut fixture =
+            if self.path.exists() { Fixture::load(&self.path)? } else { Fixture::default() };
+        let exchanges = self.exchanges.lock().unwrap().clone();
+        eprintln!("Recorded {} HTTP exchanges for trigger {}", exchanges.len(), self.key);
+        fixture.triggers.insert(self.key.clone(), exchanges);
+        fixture.save(&self.path)
+    }
+}
+
+pub struct Replayer {
+    key: String,
+    remaining: Mutex<Vec<Exchange>>,
+}
+
+impl Replayer {
+    pub fn load(path: &Path, key: String) -> Result<Self> {
+        let mut fixture = Fixture::load(path)?;
+        let exchanges = fixture
+            .triggers
+            .remove(&key)
+            .ok_or_else(|| anyhow!("No recording for trigger {} in {}", key, path.display()))?;
+        Ok(Replayer { key, remaining: Mutex::new(exchanges) })
+    }
+
+    /// Serve the first unused exchange matching the request method, URI and body
+    pub async fn send(
+        &self,
+        request: hyper::Request<HyperOutgoingBody>,
+        config: OutgoingRequestConfig,
+    ) -> Result<IncomingResponse, ErrorCode> {
+        let (parts, body) = request.into_parts();
+        let request_body = Body::from_bytes(&body.collect().await?.to_bytes());
+        let method = parts.method.to_string();
+        let uri = parts.uri.to_string();
+
+        let exchange = {
+            let mut remaining = self.remaining.lock().unwrap();
+            let position = remaining.iter().position(|exchange| {
+                exchange.method == method
+                    && exchange.uri == uri
+                    && exchange.request_body == request_body
+            });
+            position.map(|i| remaining.remove(i))
+        }
+        .ok_or_else(|| {
+            ErrorCode::InternalError(Some(format!(
+                "No recorded response for {} {} in trigger {}",
+                method, uri, self.key
+            )))
+        })?;
+
+        let mut response = hyper::Response::builder().status(exchange.status);
+        for (name, value) in &exchange.headers {
+            response = response.header(name, value);
+        }
+        let response_body = exchange
+            .response_body
+            .to_bytes()
+            .map_err(|e| ErrorCode::InternalError(Some(e.to_string())))?;
+        let resp = response
+            .body(full_body(response_body))
+            .map_err(|e| ErrorCode::InternalError(Some(e.to_string())))?;
+
+        Ok(IncomingResponse {
+            resp,
+            worker: None,
+            between_bytes_timeout: config.between_bytes_timeout,
+        })
+    }
+}
+
+fn full_body(bytes: Bytes) -> HyperOutgoingBody {
+    Full::new(bytes).map_err(|never| match never {}).boxed()
+}
@@ -0,0 +1,120 @@
+use crate::fixture::HttpMode;
+use anyhow::anyhow;
+use std::str::FromStr;
+use wasmtime::component::ResourceTable;
+use wasmtime_wasi::{IoView, WasiCtx, WasiCtxBuilder, WasiView};
+use wasmtime_wasi_http::{
+    body::HyperOutgoingBody,
+    types::{default_send_request, HostFutureIncomingResponse, OutgoingRequestConfig},
+    HttpResult, WasiHttpCtx, WasiHttpView,
+};
+
+wasmtime::component::bindgen!({
+    world: "layer-trigger-world",
+    path: "wit",
+    async: true,
+});
+
+pub use wavs::worker::layer_types::{
+    CosmosChainConfig, EthAddress, EthChainConfig, EthEventLogData, LogLevel, TriggerConfig,
+    TriggerData, TriggerDataEthContractEvent, TriggerSource, TriggerSourceEthContractEvent,
+};
+
+/// A chain exposed to the component, parsed from `NAME=HTTP_URL[@CHAIN_ID]`
+#[derive(Clone, Debug)]
+pub struct ChainEndpoint {
+    pub name: String,
+    pub http_endpoint: String,
+    pub chain_id: String,
+}
+
+impl FromStr for ChainEndpoint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, endpoint) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected NAME=HTTP_URL[@CHAIN_ID], got {}", s))?;
+        let (http_endpoint, chain_id) = match endpoint.rsplit_once('@') {
+            Some((url, chain_id)) => (url, chain_id),
+            None => (endpoint, "31337"),
+        };
+        Ok(ChainEndpoint {
+            name: name.to_string(),
+            http_endpoint: http_endpoint.to_string(),
+            chain_id: chain_id.to_string(),
+        })
+    }
+}
+
+/// Store data backing the WASI, WASI HTTP and WAVS host imports
+pub struct HostState {
+    table: ResourceTable,
+    wasi: WasiCtx,
+    http: WasiHttpCtx,
+    http_mode: HttpMode,
+    chains: Vec<ChainEndpoint>,
+}
+
+impl HostState {
+    pub fn new(chains: Vec<ChainEndpoint>, envs: &[(String, String)], http_mode: HttpMode) -> Self {
+        let wasi = WasiCtxBuilder::new().inherit_stdio().envs(envs).build();
+        HostState { table: ResourceTable::new(), wasi, http: WasiHttpCtx::new(), http_mode, chains }
+    }
+}
+
+impl IoView for HostState {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+}
+
+impl WasiView for HostState {
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.wasi
+    }
+}
+
+impl WasiHttpView for HostState {
+    fn ctx(&mut self) -> &mut WasiHttpCtx {
+        &mut self.http
+    }
+
+    fn send_request(
+        &mut self,
+        request: hyper::Request<HyperOutgoingBody>,
+        config: OutgoingRequestConfig,
+    ) -> HttpResult<HostFutureIncomingResponse> {
+        let handle =
+            match self.http_mode.clone() {
+                HttpMode::Live => return Ok(default_send_request(request, config)),
+                HttpMode::Record(recorder) => wasmtime_wasi::runtime::spawn(async move {
+                    Ok(recorder.send(request, config).await)
+                }),
+                HttpMode::Replay(replayer) => wasmtime_wasi::runtime::spawn(async move {
+                    Ok(replayer.send(request, config).await)
+                }),
+            };
+        Ok(HostFutureIncomingResponse::pending(handle))
+    }
+}
+
+impl wavs::worker::layer_types::Host for HostState {}
+
+impl host::Host for HostState {
+    async fn get_eth_chain_config(&mut self, chain_name: String) -> Option<EthChainConfig> {
+        self.chains.iter().find(|chain| chain.name == chain_name).map(|chain| EthChainConfig {
+            chain_id: chain.chain_id.clone(),
+            ws_endpoint: None,
+            http_endpoint: Some(chain.http_endpoint.clone()),
+        })
+    }
+
+    async fn get_cosmos_chain_config(&mut self, _chain_name: String) -> Option<CosmosChainConfig> {
+        None
+    }
+
+    async fn log(&mut self, level: LogLevel, message: String) {
+        eprintln!("[{:?}] {}", level, message);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::host::{
+    EthAddress, EthEventLogData, TriggerAction, TriggerConfig, TriggerData,
+    TriggerDataEthContractEvent, TriggerSource, TriggerSourceEthContractEvent,
+};
+use crate::TriggerKind;
+use alloy_primitives::{keccak256, Address, Bytes, LogData, U256};
+use alloy_sol_macro::sol;
+use alloy_sol_types::SolValue;
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+sol!("../../src/interfaces/IWavsNftServiceTypes.sol");
+
+use IWavsNftServiceTypes::{
+    WavsMintResult, WavsNftTrigger, WavsResponse, WavsTriggerType, WavsUpdateResult,
+};
+
+/// Build a `WavsNftTrigger` event as emitted by `WavsMinter` and `WavsNft`
+pub fn nft_trigger(
+    sender: Address,
+    prompt: String,
+    trigger_id: u64,
+    kind: TriggerKind,
+    token_id: U256,
+    options: Bytes,
+) -> WavsNftTrigger {
+    let wavs_trigger_type = match kind {
+        TriggerKind::Mint => WavsTriggerType::MINT,
+        TriggerKind::Update => WavsTriggerType::UPDATE,
+        TriggerKind::BatchMint => WavsTriggerType::BATCH_MINT,
+        TriggerKind::Reveal => WavsTriggerType::REVEAL,
+    };
+    WavsNftTrigger {
+        sender,
+        prompt,
+        triggerId: trigger_id,
+        wavsTriggerType: wavs_trigger_type as u8,
+        tokenId: token_id,
+        options,
+    }
+}
+
+/// Wrap an event log into the trigger action WAVS delivers for contract events
+pub fn eth_trigger_action(
+    chain_name: &str,
+    contract: Address,
+    block_height: u64,
+    log: LogData,
+) -> TriggerAction {
+    let event_hash = log.topics().first().map(|topic| topic.to_vec()).unwrap_or_default();
+    let contract_address = EthAddress { raw_bytes: contract.to_vec() };
+
+    TriggerAction {
+        config: TriggerConfig {
+            service_id: "default".to_string(),
+            workflow_id: "default".to_string(),
+            trigger_source: TriggerSource::EthContractEvent(TriggerSourceEthContractEvent {
+                address: contract_address.clone(),
+                chain_name: chain_name.to_string(),
+                event_hash,
+            }),
+        },
+        data: TriggerData::EthContractEvent(TriggerDataEthContractEvent {
+            contract_address,
+            chain_name: chain_name.to_string(),
+            log: EthEventLogData {
+                topics: log.topics().iter().map(|topic| topic.to_vec()).collect(),
+                data: log.data.to_vec(),
+            },
+            block_height,
+        }),
+    }
+}
+
+/// Wrap raw input, as `wavs-cli exec --input` does. `0x` prefixed input is hex decoded.
+pub fn raw_trigger_action(input: &str) -> Result<TriggerAction> {
+    let bytes = match input.strip_prefix("0x") {
+        Some(hex_input) => hex::decode(hex_input).context("Failed to decode hex input")?,
+        None => input.as_bytes().to_vec(),
+    };
+
+    Ok(TriggerAction {
+        config: TriggerConfig {
+            service_id: "default".to_string(),
+            workflow_id: "default".to_string(),
+            trigger_source: TriggerSource::Manual,
+        },
+        data: TriggerData::Raw(bytes),
+    })
+}
+
+/// Key identifying a trigger in fixture files: the keccak256 of its event log or raw input
+pub fn trigger_key(action: &TriggerAction) -> String {
+    let bytes = match &action.data {
+        TriggerData::EthContractEvent(event) => {
+            let mut bytes = event.log.topics.concat();
+            bytes.extend_from_slice(&event.log.data);
+            bytes
+        }
+        TriggerData::Raw(bytes) => bytes.clone(),
+        TriggerData::CosmosContractEvent(event) => format!("{:?}", event.event).into_bytes(),
+    };
+    keccak256(bytes).to_string()
+}
+
+/// Decode the component output as a `WavsResponse`, keeping its hex, falling back to hex alone for other outputs
+pub fn decode_output(output: &[u8]) -> Value {
+    let Ok(response) = WavsResponse::abi_decode(output, true) else {
+        return json!({ "raw": format!("0x{}", hex::encode(output)) });
+    };
+
+    let data = match response.wavsTriggerType {
+        WavsTriggerType::MINT => match WavsMintResult::abi_decode(&response.data, true) {
+            Ok(result) => mint_result_json(&result),
+            Err(e) => json!({ "error": e.to_string() }),
+        },
+        WavsTriggerType::BATCH_MINT => {
+            match Vec::<WavsMintResult>::abi_decode(&response.data, true) {
+                Ok(results) => results.iter().map(mint_result_json).collect(),
+                Err(e) => json!({ "error": e.to_string() }),
+            }
+        }
+        WavsTriggerType::UPDATE | WavsTriggerType::REVEAL => {
+            match WavsUpdateResult::abi_decode(&response.data, true) {
+                Ok(result) => json!({
+                    "triggerId": result.triggerId,
+                    "owner": result.owner.to_string(),
+                    "tokenURI": result.tokenURI,
+                    "tokenId": result.tokenId.to_string(),
+                }),
+                Err(e) => json!({ "error": e.to_string() }),
+            }
+        }
+        _ => json!({ "raw": format!("0x{}", hex::encode(&response.data)) }),
+    };
+
+    json!({
+        "triggerId": response.triggerId,
+        "wavsTriggerType": match response.wavsTriggerType {
+            WavsTriggerType::MINT => "MINT",
+            WavsTriggerType::UPDATE => "UPDATE",
+            WavsTriggerType::BATCH_MINT => "BATCH_MINT",
+            WavsTriggerType::REVEAL => "REVEAL",
+            _ => "UNKNOWN",
+        },
+        "data": data,
+        // The bytes to submit by hand when no operator did
+        "raw": format!("0x{}", hex::encode(output)),
+    })
+}
+
+fn mint_result_json(result: &WavsMintResult) -> Value {
+    json!({
+        "triggerId": result.triggerId,
+        "recipient": result.recipient.to_string(),
+        "tokenURI": result.tokenURI,
+    })
+}